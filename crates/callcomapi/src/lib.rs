@@ -3,7 +3,27 @@
 //! This crate provides a unified entry point for using COM API macros and utilities.
 
 pub use callcomapi_macros::{com_thread, with_com};
-pub use callcomapi_runtime::{ComModel, init_com};
+pub use callcomapi_runtime::{
+    ApartmentConfig, ApartmentExecutor, ApartmentJoinSet, ApartmentState, ApartmentStats,
+    ApartmentStream, BreakerStats, CacheStats, CallError, CallerHandle, CircuitBreaker,
+    CircuitOpen, CircuitPolicy, CircuitState, CircuitStats, ComModel, ComRuntime,
+    CompletionExecutor, DetachedError, HostedApartment, HungTask, HungTaskPolicy, InlineGuard,
+    PendingCall, RestartPolicy, ResultCache, RetryPolicy, RuntimeEvent, RuntimeStats,
+    ScheduledTask, Session, SessionClosed, SessionReceiver, SessionSender, StreamClosed,
+    StreamSink, WatchdogConfig, add_context_hook, assert_apartment, call_batch, call_batch_async,
+    call_cached, call_cached_async, call_keyed, call_single_flight, call_single_flight_async,
+    call_stream, call_sync_ephemeral, call_sync_reentrant, call_sync_scoped, call_with_breaker,
+    call_with_breaker_async, call_with_callback, call_with_executor, call_with_retry,
+    call_with_retry_async, clear_context_hooks, clear_event_handler, configure_apartment,
+    current_apartment, disable_watchdog, enable_watchdog, host_apartment, init_com, inline_guard,
+    is_runtime_worker, is_transient_hresult, open_session, run_main_sta, schedule_after,
+    schedule_every, set_error_sink, set_event_handler, spawn_detached, start_call, stats,
+};
+
+#[cfg(feature = "tokio")]
+pub use callcomapi_runtime::TokioMode;
+#[cfg(feature = "fault-injection")]
+pub use callcomapi_runtime::{Fault, FaultGuard};
 
 #[doc(hidden)]
 pub use callcomapi_runtime as __runtime;
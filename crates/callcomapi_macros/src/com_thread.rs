@@ -1,39 +1,329 @@
 use proc_macro::TokenStream;
 use proc_macro2::Span;
-use quote::quote;
-use syn::{Ident, ItemFn, parse_macro_input};
+use quote::{format_ident, quote};
+use syn::punctuated::Punctuated;
+use syn::{Ident, ItemFn, Token, parse_macro_input};
 
 // Lightweight proc-macro: generate a wrapper that delegates execution to
 // `callcomapi_runtime`. The macro ensures parameter/return types are
 // `Send + 'static` (compile-time checks) and maps the attribute (STA/MTA)
 // to the runtime `ComModel`.
 
-pub fn inner_com_thread(attr: TokenStream, item: TokenStream) -> TokenStream {
-    // parse and normalize attribute (accepts STA/MTA variants)
-    let model_kind_str = if attr.is_empty() || attr.to_string().trim().is_empty() {
-        "STA"
-    } else {
-        let ident: syn::Ident = match syn::parse(attr.clone()) {
-            Ok(i) => i,
-            Err(_) => {
-                return syn::Error::new(
-                    proc_macro2::Span::call_site(),
-                    "invalid attribute syntax; expected STA or MTA without quotes",
+/// Options accepted by `#[com_thread(...)]`, in any order.
+struct ComThreadArgs {
+    model_kind_str: &'static str,
+    /// `detached`: fire-and-forget, the wrapper returns immediately.
+    detached: bool,
+    /// `scoped`: sync functions may take borrowed parameters.
+    scoped: bool,
+    /// `ephemeral`: each call gets a fresh apartment thread.
+    ephemeral: bool,
+    /// `runtime = path`: the `ComRuntime` to run on instead of the global one.
+    runtime: Option<syn::Expr>,
+    /// `key = expr`: calls with equal keys run in order on one worker.
+    key: Option<syn::Expr>,
+    /// `single_flight`: concurrent identical calls share one execution.
+    single_flight: bool,
+    /// `single_flight(key = expr)`: what makes calls identical, instead of
+    /// all parameters.
+    single_flight_key: Option<syn::Expr>,
+    /// `cache(ttl = "60s", capacity = 128)`: results are reused until they
+    /// expire.
+    cache: Option<CacheArgs>,
+    /// `retry(max = 3, backoff = "exp")`: calls failing with a transient
+    /// HRESULT are queued again.
+    retry: Option<RetryArgs>,
+    /// `circuit_breaker(failures = 5, window = "60s", cooldown = "30s")`:
+    /// calls fail fast after repeated failures.
+    circuit_breaker: Option<CircuitArgs>,
+}
+
+struct CacheArgs {
+    ttl_ms: u64,
+    capacity: usize,
+}
+
+impl CacheArgs {
+    fn parse(list: &syn::MetaList) -> syn::Result<Self> {
+        let options =
+            list.parse_args_with(Punctuated::<syn::MetaNameValue, Token![,]>::parse_terminated)?;
+        let mut ttl_ms = None;
+        let mut capacity = 128;
+        for nv in options {
+            if nv.path.is_ident("ttl") {
+                ttl_ms = Some(lit_millis(&nv.value)?);
+            } else if nv.path.is_ident("capacity") {
+                capacity = lit_int(&nv.value)?;
+            } else {
+                return Err(syn::Error::new_spanned(
+                    nv.path,
+                    "unsupported cache option, expected ttl or capacity",
+                ));
+            }
+        }
+        let ttl_ms = ttl_ms.ok_or_else(|| {
+            syn::Error::new_spanned(list, "cache requires a ttl, e.g. cache(ttl = \"60s\")")
+        })?;
+        Ok(CacheArgs { ttl_ms, capacity })
+    }
+}
+
+struct RetryArgs {
+    max: u32,
+    /// `"none"`, `"fixed"` or `"exp"`.
+    backoff: String,
+    delay_ms: u64,
+    max_delay_ms: u64,
+    classify: Option<syn::Expr>,
+}
+
+impl RetryArgs {
+    fn parse(list: &syn::MetaList) -> syn::Result<Self> {
+        let options =
+            list.parse_args_with(Punctuated::<syn::MetaNameValue, Token![,]>::parse_terminated)?;
+        let mut args = RetryArgs {
+            max: 3,
+            backoff: "exp".to_string(),
+            delay_ms: 100,
+            max_delay_ms: 10_000,
+            classify: None,
+        };
+        for nv in options {
+            if nv.path.is_ident("max") {
+                args.max = lit_int(&nv.value)?;
+            } else if nv.path.is_ident("backoff") {
+                let backoff = match &nv.value {
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(s),
+                        ..
+                    }) => s.value(),
+                    _ => String::new(),
+                };
+                args.backoff = match backoff.as_str() {
+                    "none" => "none",
+                    "fixed" => "fixed",
+                    "exp" | "exponential" => "exp",
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            nv.value,
+                            "invalid backoff, expected \"none\", \"fixed\" or \"exp\"",
+                        ));
+                    }
+                }
+                .to_string();
+            } else if nv.path.is_ident("delay") {
+                args.delay_ms = lit_millis(&nv.value)?;
+            } else if nv.path.is_ident("max_delay") {
+                args.max_delay_ms = lit_millis(&nv.value)?;
+            } else if nv.path.is_ident("classify") {
+                args.classify = Some(nv.value);
+            } else {
+                return Err(syn::Error::new_spanned(
+                    nv.path,
+                    "unsupported retry option, expected max, backoff, delay, max_delay or classify",
+                ));
+            }
+        }
+        Ok(args)
+    }
+
+    /// Expression building the runtime's `RetryPolicy`.
+    fn policy(&self) -> proc_macro2::TokenStream {
+        let max = self.max;
+        let (delay_ms, max_delay_ms) = (self.delay_ms, self.max_delay_ms);
+        let policy = quote! { ::callcomapi::__runtime::RetryPolicy };
+        let policy = match self.backoff.as_str() {
+            "none" => quote! { #policy::immediate(#max) },
+            "fixed" => quote! {
+                #policy::fixed(#max, ::std::time::Duration::from_millis(#delay_ms))
+            },
+            _ => quote! {
+                #policy::exponential(
+                    #max,
+                    ::std::time::Duration::from_millis(#delay_ms),
+                    ::std::time::Duration::from_millis(#max_delay_ms),
                 )
-                .to_compile_error()
-                .into();
+            },
+        };
+        match &self.classify {
+            Some(classify) => quote! { #policy.classify(#classify) },
+            None => policy,
+        }
+    }
+}
+
+struct CircuitArgs {
+    failures: u32,
+    window_ms: u64,
+    cooldown_ms: u64,
+}
+
+impl Default for CircuitArgs {
+    fn default() -> Self {
+        CircuitArgs {
+            failures: 5,
+            window_ms: 60_000,
+            cooldown_ms: 30_000,
+        }
+    }
+}
+
+impl CircuitArgs {
+    fn parse(list: &syn::MetaList) -> syn::Result<Self> {
+        let options =
+            list.parse_args_with(Punctuated::<syn::MetaNameValue, Token![,]>::parse_terminated)?;
+        let mut args = CircuitArgs::default();
+        for nv in options {
+            if nv.path.is_ident("failures") {
+                args.failures = lit_int(&nv.value)?;
+                if args.failures == 0 {
+                    return Err(syn::Error::new_spanned(
+                        nv.value,
+                        "failures must be at least 1",
+                    ));
+                }
+            } else if nv.path.is_ident("window") {
+                args.window_ms = lit_millis(&nv.value)?;
+            } else if nv.path.is_ident("cooldown") {
+                args.cooldown_ms = lit_millis(&nv.value)?;
+            } else {
+                return Err(syn::Error::new_spanned(
+                    nv.path,
+                    "unsupported circuit_breaker option, expected failures, window or cooldown",
+                ));
             }
+        }
+        Ok(args)
+    }
+}
+
+impl ComThreadArgs {
+    fn parse(attr: TokenStream) -> syn::Result<Self> {
+        let mut args = ComThreadArgs {
+            model_kind_str: "STA",
+            detached: false,
+            scoped: false,
+            ephemeral: false,
+            runtime: None,
+            key: None,
+            single_flight: false,
+            single_flight_key: None,
+            cache: None,
+            retry: None,
+            circuit_breaker: None,
         };
-        match ident.to_string().to_uppercase().as_str() {
-            "MTA" | "MULTI" | "MULTITHREADED" => "MTA",
-            "STA" | "APARTMENT" | "APARTMENTTHREADED" => "STA",
-            _ => {
-                return syn::Error::new_spanned(ident, "invalid COM model, expected STA or MTA")
-                    .to_compile_error()
-                    .into();
+        if attr.is_empty() {
+            return Ok(args);
+        }
+
+        let metas =
+            syn::parse::Parser::parse(Punctuated::<syn::Meta, Token![,]>::parse_terminated, attr)
+                .map_err(|e| {
+                syn::Error::new(
+                    e.span(),
+                    "invalid attribute syntax; expected STA or MTA without quotes",
+                )
+            })?;
+
+        for meta in metas {
+            if let syn::Meta::NameValue(nv) = &meta
+                && nv.path.is_ident("runtime")
+            {
+                args.runtime = Some(nv.value.clone());
+                continue;
+            }
+            if let syn::Meta::NameValue(nv) = &meta
+                && nv.path.is_ident("key")
+            {
+                args.key = Some(nv.value.clone());
+                continue;
+            }
+            if let syn::Meta::List(list) = &meta
+                && list.path.is_ident("cache")
+            {
+                args.cache = Some(CacheArgs::parse(list)?);
+                continue;
+            }
+            if let syn::Meta::List(list) = &meta
+                && list.path.is_ident("retry")
+            {
+                args.retry = Some(RetryArgs::parse(list)?);
+                continue;
+            }
+            if let syn::Meta::List(list) = &meta
+                && list.path.is_ident("circuit_breaker")
+            {
+                args.circuit_breaker = Some(CircuitArgs::parse(list)?);
+                continue;
+            }
+            if let syn::Meta::List(list) = &meta
+                && list.path.is_ident("single_flight")
+            {
+                let nv: syn::MetaNameValue = list.parse_args()?;
+                if !nv.path.is_ident("key") {
+                    return Err(syn::Error::new_spanned(
+                        nv.path,
+                        "expected single_flight(key = expr)",
+                    ));
+                }
+                args.single_flight = true;
+                args.single_flight_key = Some(nv.value);
+                continue;
+            }
+            let syn::Meta::Path(path) = &meta else {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "unsupported com_thread option",
+                ));
+            };
+            let Some(ident) = path.get_ident() else {
+                return Err(syn::Error::new_spanned(
+                    path,
+                    "unsupported com_thread option",
+                ));
+            };
+            if ident == "detached" {
+                args.detached = true;
+                continue;
             }
+            if ident == "scoped" {
+                args.scoped = true;
+                continue;
+            }
+            if ident == "ephemeral" {
+                args.ephemeral = true;
+                continue;
+            }
+            if ident == "single_flight" {
+                args.single_flight = true;
+                continue;
+            }
+            if ident == "circuit_breaker" {
+                args.circuit_breaker = Some(CircuitArgs::default());
+                continue;
+            }
+            args.model_kind_str = match ident.to_string().to_uppercase().as_str() {
+                "MTA" | "MULTI" | "MULTITHREADED" => "MTA",
+                "STA" | "APARTMENT" | "APARTMENTTHREADED" => "STA",
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        ident,
+                        "invalid COM model or option, expected STA, MTA, detached, scoped, ephemeral, single_flight or circuit_breaker",
+                    ));
+                }
+            };
         }
+        Ok(args)
+    }
+}
+
+pub fn inner_com_thread(attr: TokenStream, item: TokenStream) -> TokenStream {
+    // parse and normalize attribute (accepts STA/MTA variants plus options)
+    let args = match ComThreadArgs::parse(attr) {
+        Ok(args) => args,
+        Err(e) => return e.to_compile_error().into(),
     };
+    let model_kind_str = args.model_kind_str;
 
     // parse the original function and extract signature pieces
     let func = parse_macro_input!(item as ItemFn);
@@ -54,10 +344,91 @@ pub fn inner_com_thread(attr: TokenStream, item: TokenStream) -> TokenStream {
         .collect();
 
     let is_async = sig.asyncness.is_some();
+    let stream_item = stream_item_type(output);
+    if is_async && stream_item.is_some() {
+        return syn::Error::new_spanned(
+            sig.asyncness,
+            "stream functions must not be async; the body produces items synchronously",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    // each of these decides how the call is made, so at most one applies;
+    // the last column marks those that block the caller's thread
+    let options = [
+        ("detached", args.detached, false),
+        ("scoped", args.scoped, true),
+        ("ephemeral", args.ephemeral, true),
+        ("key", args.key.is_some(), true),
+        ("single_flight", args.single_flight, false),
+        ("cache", args.cache.is_some(), false),
+        ("retry", args.retry.is_some(), false),
+        ("circuit_breaker", args.circuit_breaker.is_some(), false),
+        ("a Stream return type", stream_item.is_some(), false),
+    ];
+    let mut chosen = options.iter().filter(|(_, set, _)| *set);
+    if let (Some((first, ..)), Some((second, ..))) = (chosen.next(), chosen.next()) {
+        return syn::Error::new_spanned(sig, format!("{first} cannot be combined with {second}"))
+            .to_compile_error()
+            .into();
+    }
+    let blocking = options.iter().find(|(_, set, blocks)| *set && *blocks);
+    if let (Some((name, ..)), true) = (blocking, is_async) {
+        return syn::Error::new_spanned(sig.asyncness, format!("{name} functions must be sync"))
+            .to_compile_error()
+            .into();
+    }
+
+    // the cache lives in a `static` next to the function, which an impl
+    // block cannot hold
+    if let (Some(_), Some(span)) = (&args.cache, associated_fn_span(sig)) {
+        return syn::Error::new(
+            span,
+            "cache needs a free function, not a method or associated function",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    // without an explicit key, calls are identical when all their
+    // parameters are equal
+    let params = match named_params(inputs) {
+        Ok(params) => params,
+        Err(pat) if args.retry.is_some() => {
+            return syn::Error::new_spanned(pat, "retried functions need named parameters")
+                .to_compile_error()
+                .into();
+        }
+        Err(pat) if args.cache.is_some() => {
+            return syn::Error::new_spanned(pat, "cached functions need named parameters")
+                .to_compile_error()
+                .into();
+        }
+        Err(pat) if args.single_flight && args.single_flight_key.is_none() => {
+            return syn::Error::new_spanned(
+                pat,
+                "single_flight needs named parameters or an explicit single_flight(key = expr)",
+            )
+            .to_compile_error()
+            .into();
+        }
+        Err(_) => Vec::new(),
+    };
+    let param_names: Vec<_> = params.iter().map(|(name, _)| name).collect();
+    // owned, since the parameters move into the task
+    let params_key = quote! { (#(::std::clone::Clone::clone(&#param_names),)*) };
+    let single_flight_key = match &args.single_flight_key {
+        Some(key) => quote! { #key },
+        None => params_key.clone(),
+    };
 
-    // generate compile-time assertions enforcing `Send + 'static`
+    // generate compile-time assertions enforcing `Send + 'static`; scoped
+    // calls block the caller, so parameters only need to be `Send`, which
+    // the runtime's closure bound already checks
     let mut assert_bounds = Vec::new();
-    for (idx, arg_type) in arg_types.iter().enumerate() {
+    let param_types = if args.scoped { &[][..] } else { &arg_types[..] };
+    for (idx, arg_type) in param_types.iter().enumerate() {
         let assert_fn_name = Ident::new(
             &format!("_assert_param_{}_is_send_static", idx),
             Span::call_site(),
@@ -71,9 +442,25 @@ pub fn inner_com_thread(attr: TokenStream, item: TokenStream) -> TokenStream {
         });
     }
 
-    let ret_type_for_assert = match output {
-        syn::ReturnType::Default => quote! { () },
-        syn::ReturnType::Type(_, ty) => quote! { #ty },
+    if args.detached {
+        let returns_unit = match output {
+            syn::ReturnType::Default => true,
+            syn::ReturnType::Type(_, ty) => {
+                matches!(&**ty, syn::Type::Tuple(t) if t.elems.is_empty())
+            }
+        };
+        if is_async || !returns_unit {
+            return syn::Error::new_spanned(sig, "detached functions must be sync and return ()")
+                .to_compile_error()
+                .into();
+        }
+    }
+
+    // for stream functions the items are what crosses the thread boundary
+    let ret_type_for_assert = match (output, stream_item) {
+        (_, Some(item)) => quote! { #item },
+        (syn::ReturnType::Default, None) => quote! { () },
+        (syn::ReturnType::Type(_, ty), None) => quote! { #ty },
     };
     let assert_ret_fn = Ident::new("_assert_return_is_send_static", Span::call_site());
     assert_bounds.push(quote! {
@@ -97,25 +484,328 @@ pub fn inner_com_thread(attr: TokenStream, item: TokenStream) -> TokenStream {
         quote! { ::callcomapi::__runtime::ComModel::STA }
     };
 
+    // the runtime instance executing the task
+    let runtime = match &args.runtime {
+        Some(path) => quote! { (#path) },
+        None => quote! { ::callcomapi::__runtime::ComRuntime::global() },
+    };
+
+    // label reported in runtime stats and watchdog reports
+    let fn_name = &sig.ident;
+    let label = quote! { concat!(module_path!(), "::", stringify!(#fn_name)) };
+
     // generate wrapper that delegates to runtime; parameters are captured
     // by `move` into the task closure so ownership moves across threads.
-    let expanded = if is_async {
+    let expanded = if let Some(cache) = &args.cache {
+        // the cache and its invalidation functions sit next to the wrapper
+        let cache_static = Ident::new(
+            &format!("__CALLCOMAPI_CACHE_{}", fn_name.to_string().to_uppercase()),
+            fn_name.span(),
+        );
+        let ret_type = match output {
+            syn::ReturnType::Default => quote! { () },
+            syn::ReturnType::Type(_, ty) => quote! { #ty },
+        };
+        let (ttl_ms, capacity) = (cache.ttl_ms, cache.capacity);
+        let call = if is_async {
+            quote! {
+                #runtime.call_cached_async_labeled(#runtime_model_token, &#cache_static, __callcomapi_key, #label, move || {
+                    ::callcomapi::__runtime::block_on(async move { #block })
+                }).await
+            }
+        } else {
+            quote! {
+                #runtime.call_cached_labeled(#runtime_model_token, &#cache_static, __callcomapi_key, #label, move || { (|| #block)() })
+            }
+        };
+        let invalidate = format_ident!("{}_invalidate", fn_name);
+        let invalidate_all = format_ident!("{}_invalidate_all", fn_name);
+        let invalidate_doc =
+            format!("Drop the cached result of [`{fn_name}`] for these arguments.");
+        let invalidate_all_doc = format!("Drop every cached result of [`{fn_name}`].");
+        let param_types: Vec<_> = params.iter().map(|(_, ty)| ty).collect();
         quote! {
+            static #cache_static: ::callcomapi::__runtime::ResultCache<(#(#param_types,)*), #ret_type> =
+                ::callcomapi::__runtime::ResultCache::new(
+                    #label,
+                    ::std::time::Duration::from_millis(#ttl_ms),
+                    #capacity,
+                );
+
             #vis #sig {
                 #compile_time_checks
-                ::callcomapi::__runtime::call_async(#runtime_model_token, move || {
+                let __callcomapi_key = #params_key;
+                #call
+            }
+
+            #[doc = #invalidate_doc]
+            #vis fn #invalidate(#(#param_names: #param_types),*) {
+                #cache_static.invalidate(&(#(#param_names,)*));
+            }
+
+            #[doc = #invalidate_all_doc]
+            #vis fn #invalidate_all() {
+                #cache_static.invalidate_all();
+            }
+        }
+    } else if let Some(retry) = &args.retry {
+        // every attempt runs the body on its own clones of the parameters
+        let policy = retry.policy();
+        let body = if is_async {
+            quote! { ::callcomapi::__runtime::block_on(async move { #block }) }
+        } else {
+            quote! { (|| #block)() }
+        };
+        // `mut` moves from the wrapper's parameters to the clones
+        let mut sig = sig.clone();
+        let mut rebinds = Vec::new();
+        for arg in &mut sig.inputs {
+            if let syn::FnArg::Typed(pat) = arg
+                && let syn::Pat::Ident(ident) = &mut *pat.pat
+            {
+                let mutability = ident.mutability.take();
+                let name = &ident.ident;
+                rebinds.push(quote! {
+                    let #mutability #name = ::std::clone::Clone::clone(&#name);
+                });
+            }
+        }
+        let call = quote! {
+            move || {
+                #(#rebinds)*
+                #body
+            }
+        };
+        let call = if is_async {
+            quote! { #runtime.call_with_retry_async_labeled(#runtime_model_token, #policy, #label, #call).await }
+        } else {
+            quote! { #runtime.call_with_retry_labeled(#runtime_model_token, #policy, #label, #call) }
+        };
+        quote! {
+            #vis #sig {
+                #compile_time_checks
+                #call
+            }
+        }
+    } else if let Some(circuit) = &args.circuit_breaker {
+        // declared inside the wrapper, so methods get one too
+        let breaker_static = Ident::new("__CALLCOMAPI_BREAKER", Span::call_site());
+        let (failures, window_ms, cooldown_ms) =
+            (circuit.failures, circuit.window_ms, circuit.cooldown_ms);
+        let call = if is_async {
+            quote! {
+                #runtime.call_with_breaker_async_labeled(#runtime_model_token, &#breaker_static, #label, move || {
+                    ::callcomapi::__runtime::block_on(async move { #block })
+                }).await
+            }
+        } else {
+            quote! {
+                #runtime.call_with_breaker_labeled(#runtime_model_token, &#breaker_static, #label, move || { (|| #block)() })
+            }
+        };
+        quote! {
+            #vis #sig {
+                #compile_time_checks
+                static #breaker_static: ::callcomapi::__runtime::CircuitBreaker =
+                    ::callcomapi::__runtime::CircuitBreaker::new(
+                        #label,
+                        ::callcomapi::__runtime::CircuitPolicy::new(
+                            #failures,
+                            ::std::time::Duration::from_millis(#window_ms),
+                            ::std::time::Duration::from_millis(#cooldown_ms),
+                        ),
+                    );
+                #call
+            }
+        }
+    } else if args.detached {
+        quote! {
+            #vis #sig {
+                #compile_time_checks
+                #runtime.spawn_detached_labeled(#runtime_model_token, #label, move || { (|| #block)() })
+            }
+        }
+    } else if args.single_flight && is_async {
+        // evaluated up front, before the parameters move into the task
+        quote! {
+            #vis #sig {
+                #compile_time_checks
+                let __callcomapi_key = #single_flight_key;
+                #runtime.call_single_flight_async_labeled(#runtime_model_token, __callcomapi_key, #label, move || {
                     ::callcomapi::__runtime::block_on(async move { #block })
                 }).await
             }
         }
+    } else if args.single_flight {
+        quote! {
+            #vis #sig {
+                #compile_time_checks
+                let __callcomapi_key = #single_flight_key;
+                #runtime.call_single_flight_labeled(#runtime_model_token, __callcomapi_key, #label, move || { (|| #block)() })
+            }
+        }
+    } else if is_async {
+        quote! {
+            #vis #sig {
+                #compile_time_checks
+                #runtime.call_async_labeled(#runtime_model_token, #label, move || {
+                    ::callcomapi::__runtime::block_on(async move { #block })
+                }).await
+            }
+        }
+    } else if stream_item.is_some() {
+        // the body evaluates to an iterator which is drained on the worker,
+        // forwarding each item as soon as it is produced
+        quote! {
+            #vis #sig {
+                #compile_time_checks
+                #runtime.call_stream_labeled(#runtime_model_token, #label, move |sink| {
+                    for item in (move || #block)() {
+                        if sink.send(item).is_err() {
+                            break;
+                        }
+                    }
+                })
+            }
+        }
+    } else if args.ephemeral {
+        quote! {
+            #vis #sig {
+                #compile_time_checks
+                #runtime.call_sync_ephemeral_labeled(#runtime_model_token, #label, move || { (|| #block)() })
+            }
+        }
+    } else if let Some(key) = &args.key {
+        // hashed up front, since the key usually borrows a parameter that
+        // moves into the task
+        quote! {
+            #vis #sig {
+                #compile_time_checks
+                let __callcomapi_key = ::callcomapi::__runtime::__key_hash(&(#key));
+                #runtime.call_keyed_labeled(#runtime_model_token, __callcomapi_key, #label, move || { (|| #block)() })
+            }
+        }
+    } else if args.scoped {
+        quote! {
+            #vis #sig {
+                #compile_time_checks
+                #runtime.call_sync_scoped_labeled(#runtime_model_token, #label, move || { (|| #block)() })
+            }
+        }
     } else {
         quote! {
             #vis #sig {
                 #compile_time_checks
-                ::callcomapi::__runtime::call_sync(#runtime_model_token, move || { (|| #block)() })
+                #runtime.call_sync_labeled(#runtime_model_token, #label, move || { (|| #block)() })
             }
         }
     };
 
     expanded.into()
 }
+
+/// Names and types of the parameters, or the first pattern that is not a
+/// plain name.
+fn named_params(
+    inputs: &Punctuated<syn::FnArg, Token![,]>,
+) -> Result<Vec<(&Ident, &syn::Type)>, &syn::Pat> {
+    let mut params = Vec::new();
+    for arg in inputs {
+        if let syn::FnArg::Typed(pat) = arg {
+            match &*pat.pat {
+                syn::Pat::Ident(ident) => params.push((&ident.ident, &*pat.ty)),
+                other => return Err(other),
+            }
+        }
+    }
+    Ok(params)
+}
+
+/// Where the signature shows that the function belongs to an impl block:
+/// its `self` receiver or a mention of `Self`.
+fn associated_fn_span(sig: &syn::Signature) -> Option<Span> {
+    fn find_self(tokens: proc_macro2::TokenStream) -> Option<Span> {
+        tokens.into_iter().find_map(|token| match token {
+            proc_macro2::TokenTree::Ident(ident) if ident == "Self" => Some(ident.span()),
+            proc_macro2::TokenTree::Group(group) => find_self(group.stream()),
+            _ => None,
+        })
+    }
+    match sig.receiver() {
+        Some(receiver) => Some(receiver.self_token.span),
+        None => find_self(quote! { #sig }),
+    }
+}
+
+/// An integer literal option value.
+fn lit_int<N>(value: &syn::Expr) -> syn::Result<N>
+where
+    N: std::str::FromStr,
+    N::Err: std::fmt::Display,
+{
+    match value {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(n),
+            ..
+        }) => n.base10_parse(),
+        other => Err(syn::Error::new_spanned(other, "expected an integer")),
+    }
+}
+
+/// A duration option value like `"60s"`, in milliseconds.
+fn lit_millis(value: &syn::Expr) -> syn::Result<u64> {
+    let millis = match value {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(s),
+            ..
+        }) => parse_millis(&s.value()),
+        _ => None,
+    };
+    millis.ok_or_else(|| {
+        syn::Error::new_spanned(
+            value,
+            "expected a duration like \"500ms\", \"60s\", \"5m\" or \"1h\"",
+        )
+    })
+}
+
+/// Milliseconds in a duration like `"500ms"`, `"60s"`, `"5m"` or `"1h"`.
+fn parse_millis(s: &str) -> Option<u64> {
+    let unit_at = s.find(|c: char| !c.is_ascii_digit())?;
+    let (n, unit) = s.split_at(unit_at);
+    let n: u64 = n.parse().ok()?;
+    let scale = match unit {
+        "ms" => 1,
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        _ => return None,
+    };
+    n.checked_mul(scale)
+}
+
+/// Item type `T` if the function is declared as `-> impl Stream<Item = T>`.
+fn stream_item_type(output: &syn::ReturnType) -> Option<&syn::Type> {
+    let syn::ReturnType::Type(_, ty) = output else {
+        return None;
+    };
+    let syn::Type::ImplTrait(impl_trait) = &**ty else {
+        return None;
+    };
+    impl_trait.bounds.iter().find_map(|bound| {
+        let syn::TypeParamBound::Trait(bound) = bound else {
+            return None;
+        };
+        let segment = bound.path.segments.last()?;
+        if segment.ident != "Stream" {
+            return None;
+        }
+        let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+            return None;
+        };
+        args.args.iter().find_map(|arg| match arg {
+            syn::GenericArgument::AssocType(assoc) if assoc.ident == "Item" => Some(&assoc.ty),
+            _ => None,
+        })
+    })
+}
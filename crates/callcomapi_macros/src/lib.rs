@@ -61,6 +61,88 @@
 //! - `#[com_thread]` or `#[com_thread(STA)]` - Single-threaded apartment (default)
 //! - `#[com_thread(MTA)]` - Multi-threaded apartment
 //!
+//! ### Options
+//!
+//! Options follow the threading model, separated by commas:
+//!
+//! - `#[com_thread(detached)]` / `#[com_thread(MTA, detached)]` - Fire-and-forget
+//!   for sync functions returning `()`. The call returns as soon as the task is
+//!   queued; panics are reported to the runtime's error sink.
+//! - `#[com_thread(scoped)]` - For sync functions only. Parameters may borrow
+//!   (`&str`, `&[u8]`, `&Config`) because the caller blocks until the body has
+//!   finished; the return type must still be `Send + 'static`.
+//! - `#[com_thread(ephemeral)]` - For sync functions only. Each call runs on a
+//!   fresh thread that initializes COM, runs the body, uninitializes and
+//!   exits, isolating COM servers that damage their apartment.
+//! - `#[com_thread(MTA, key = host)]` - For sync functions only. Calls whose
+//!   key expressions are equal run one at a time, in call order, on the same
+//!   worker; with `ApartmentConfig::keyed_workers` other keys run in parallel.
+//!   The key may borrow the parameters.
+//! - `#[com_thread(single_flight)]` - Concurrent calls with equal parameters
+//!   share one execution and each receive a clone of its result; a panic
+//!   reaches every waiter. `single_flight(key = expr)` decides which calls
+//!   are identical instead. The parameters, or the key, must be
+//!   `Clone + Eq + Hash` and own their data; the return type must be
+//!   `Clone + Sync`.
+//! - `#[com_thread(cache(ttl = "60s", capacity = 128))]` - Results are kept
+//!   for `ttl` (`"500ms"`, `"60s"`, `"5m"`, `"1h"`) and returned for calls
+//!   with equal parameters; `capacity` defaults to 128. Generates
+//!   `<name>_invalidate(<params>)` and `<name>_invalidate_all()` next to the
+//!   function, which must be a free function returning a `Clone` type with
+//!   `Clone + Eq + Hash` parameters; methods and associated functions are
+//!   rejected. Hits and misses are reported in
+//!   `RuntimeStats::caches`.
+//! - `#[com_thread(retry(max = 3, backoff = "exp"))]` - For functions
+//!   returning `windows::core::Result`. A call failing with a transient
+//!   HRESULT (`RPC_E_CALL_REJECTED`, `RPC_E_SERVERCALL_RETRYLATER`,
+//!   `RPC_S_SERVER_UNAVAILABLE`, `RPC_S_SERVER_TOO_BUSY`) is queued again up
+//!   to `max` times (default 3). `backoff` is `"none"`, `"fixed"` or `"exp"`
+//!   (default), starting at `delay` (default `"100ms"`) and capped by
+//!   `max_delay` (default `"10s"`). `classify = path::to::fn` takes an
+//!   `HRESULT` and decides what is retried instead. Each attempt gets clones
+//!   of the parameters, which must be `Clone`.
+//! - `#[com_thread(circuit_breaker(failures = 5, window = "60s", cooldown = "30s"))]` -
+//!   For functions returning `windows::core::Result`. After `failures`
+//!   failed calls in a row within `window`, calls return a `CircuitOpen`
+//!   error (`E_ABORT`) without running until `cooldown` has passed; then one
+//!   call at a time runs as a trial, closing the breaker or opening it
+//!   again. Errors and panics count as failures. The defaults are shown
+//!   above; a bare `circuit_breaker` uses them. The state is reported in
+//!   `RuntimeStats::breakers`. Apartments take a breaker through
+//!   `ApartmentConfig::circuit_breaker`, which counts only panics and hung
+//!   tasks, not returned errors.
+//! - `#[com_thread(runtime = path::to::RUNTIME)]` - Run on the apartments of a
+//!   specific `ComRuntime` (or a `LazyLock<ComRuntime>`) instead of the
+//!   global runtime.
+//!
+//! ### Streaming Results
+//!
+//! A sync function declared to return `impl Stream<Item = T>` has a body that
+//! evaluates to an iterator. The iterator is drained on the background thread
+//! and each item reaches the caller as soon as it is produced. Dropping the
+//! stream stops the iteration.
+//!
+//! ```ignore
+//! #[com_thread]
+//! fn processor_names() -> impl Stream<Item = String> {
+//!     // build a COM enumerator here
+//!     enumerator.map(|obj| obj.name())
+//! }
+//! ```
+//!
+//! ### Tokio
+//!
+//! With the facade's `tokio` feature, async bodies run on a tokio
+//! current-thread runtime owned by the background thread, so `tokio::time`,
+//! `tokio::sync` and tokio I/O can be awaited inside them. See
+//! `ApartmentConfig::tokio_mode` to enter the caller's runtime instead.
+//!
+//! ### Testing
+//!
+//! Inside a test, `callcomapi::inline_guard()` makes `#[com_thread]` functions
+//! called from the current thread run inline, without a thread hop, so they
+//! can be stepped through and behave the same on every run.
+//!
 //! ### Workflow
 //!
 //! 1. **First call**: Spawns background thread, initializes COM, establishes message channel
@@ -0,0 +1,41 @@
+use callcomapi_macros::com_thread;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+#[com_thread(MTA, cache(ttl = "60s", capacity = 16))]
+fn processor_name(host: String, index: u32) -> String {
+    RUNS.fetch_add(1, Ordering::SeqCst);
+    format!("{host}/cpu{index}")
+}
+
+#[com_thread(cache(ttl = "500ms"))]
+async fn volume_count() -> usize {
+    2
+}
+
+#[test]
+fn test_cached_functions_reuse_results() {
+    assert_eq!(processor_name("srv".to_string(), 0), "srv/cpu0");
+    assert_eq!(processor_name("srv".to_string(), 0), "srv/cpu0");
+    assert_eq!(processor_name("srv".to_string(), 1), "srv/cpu1");
+    assert_eq!(RUNS.load(Ordering::SeqCst), 2);
+
+    processor_name_invalidate("srv".to_string(), 0);
+    processor_name("srv".to_string(), 0);
+    processor_name("srv".to_string(), 1);
+    assert_eq!(RUNS.load(Ordering::SeqCst), 3);
+
+    processor_name_invalidate_all();
+    processor_name("srv".to_string(), 1);
+    assert_eq!(RUNS.load(Ordering::SeqCst), 4);
+
+    let stats = callcomapi::stats();
+    let cache = stats
+        .cache(concat!(module_path!(), "::processor_name"))
+        .unwrap();
+    assert_eq!((cache.hits, cache.misses), (2, 4));
+
+    assert_eq!(futures::executor::block_on(volume_count()), 2);
+    volume_count_invalidate();
+}
@@ -0,0 +1,87 @@
+use callcomapi::{CircuitOpen, CircuitState, stats};
+use callcomapi_macros::com_thread;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+use std::time::Duration;
+use windows::core::{Error, HRESULT, Result};
+
+const RPC_E_DISCONNECTED: HRESULT = HRESULT(0x8001_0108_u32 as i32);
+
+static RUNS: AtomicU32 = AtomicU32::new(0);
+
+#[com_thread(MTA, circuit_breaker(failures = 2, window = "10s", cooldown = "100ms"))]
+fn disconnected_server(fail: bool) -> Result<u32> {
+    RUNS.fetch_add(1, Ordering::SeqCst);
+    if fail {
+        return Err(Error::from(RPC_E_DISCONNECTED));
+    }
+    Ok(1)
+}
+
+#[com_thread(circuit_breaker)]
+async fn guarded_async(n: u32) -> Result<u32> {
+    Ok(n + 1)
+}
+
+#[derive(Clone, Copy)]
+struct Server(u32);
+
+impl Server {
+    #[com_thread(circuit_breaker(failures = 1, window = "10s", cooldown = "10s"))]
+    fn query(self, fail: bool) -> Result<u32> {
+        match fail {
+            true => Err(Error::from(RPC_E_DISCONNECTED)),
+            false => Ok(self.0),
+        }
+    }
+
+    #[com_thread(circuit_breaker)]
+    fn connect(id: u32) -> Result<Server> {
+        Ok(Server(id))
+    }
+}
+
+#[test]
+fn test_breaker_functions_fail_fast_while_open() {
+    assert_eq!(
+        disconnected_server(true).unwrap_err().code(),
+        RPC_E_DISCONNECTED
+    );
+    assert_eq!(
+        disconnected_server(true).unwrap_err().code(),
+        RPC_E_DISCONNECTED
+    );
+    assert_eq!(
+        disconnected_server(false).unwrap_err().code(),
+        CircuitOpen::CODE
+    );
+    assert_eq!(RUNS.load(Ordering::SeqCst), 2);
+
+    let label = concat!(module_path!(), "::disconnected_server");
+    let snapshot = stats();
+    let breaker = snapshot.breaker(label).unwrap();
+    assert_eq!(breaker.circuit.state, CircuitState::Open);
+    assert_eq!(breaker.circuit.rejected, 1);
+
+    thread::sleep(Duration::from_millis(150));
+    assert_eq!(disconnected_server(false).unwrap(), 1);
+    assert_eq!(
+        stats().breaker(label).unwrap().circuit.state,
+        CircuitState::Closed
+    );
+}
+
+#[test]
+fn test_breaker_async_function() {
+    assert_eq!(futures::executor::block_on(guarded_async(1)).unwrap(), 2);
+}
+
+#[test]
+fn test_breaker_methods() {
+    let server = Server::connect(7).unwrap();
+    assert_eq!(server.query(false).unwrap(), 7);
+    assert_eq!(server.query(true).unwrap_err().code(), RPC_E_DISCONNECTED);
+    assert_eq!(server.query(false).unwrap_err().code(), CircuitOpen::CODE);
+    // each function has a breaker of its own
+    assert!(Server::connect(8).is_ok());
+}
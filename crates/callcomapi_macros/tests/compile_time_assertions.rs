@@ -59,7 +59,8 @@ async fn test_async_valid_send_types() {
 // }
 // ERROR: `&str` cannot be sent between threads safely
 //        --> lifetime references can't live in another thread
-//        --> SOLUTION: Use String instead of &str
+//        --> SOLUTION: Use String instead of &str, or #[com_thread(scoped)]
+//            for sync functions
 //
 // USE CASE 3: Custom type without Send
 // struct NotSend {
@@ -0,0 +1,25 @@
+use callcomapi::{ComModel, assert_apartment, current_apartment, is_runtime_worker};
+use callcomapi_macros::{com_thread, with_com};
+
+#[with_com]
+fn in_with_com() -> (Option<ComModel>, bool) {
+    assert_apartment!(STA);
+    (current_apartment(), is_runtime_worker())
+}
+
+#[com_thread(MTA)]
+fn in_com_thread() -> (Option<ComModel>, bool) {
+    assert_apartment!(MTA);
+    (current_apartment(), is_runtime_worker())
+}
+
+#[test]
+fn test_apartment_seen_by_macro_functions() {
+    std::thread::spawn(|| {
+        assert_eq!(in_with_com(), (Some(ComModel::STA), false));
+        assert_eq!(current_apartment(), None);
+    })
+    .join()
+    .unwrap();
+    assert_eq!(in_com_thread(), (Some(ComModel::MTA), true));
+}
@@ -0,0 +1,34 @@
+use callcomapi_macros::com_thread;
+use std::sync::mpsc::Sender;
+use std::thread;
+
+mod common;
+
+#[com_thread(detached)]
+fn report_thread(tx: Sender<thread::ThreadId>) {
+    common::call_com_api().unwrap();
+    tx.send(thread::current().id()).unwrap();
+}
+
+#[com_thread(MTA, detached)]
+fn report_thread_mta(tx: Sender<thread::ThreadId>) {
+    tx.send(thread::current().id()).unwrap();
+}
+
+#[com_thread]
+fn sta_thread() -> thread::ThreadId {
+    thread::current().id()
+}
+
+#[test]
+fn test_detached_runs_on_apartment_thread() {
+    let (tx, rx) = std::sync::mpsc::channel();
+    report_thread(tx.clone());
+    report_thread_mta(tx);
+
+    let sta = rx.recv().unwrap();
+    let mta = rx.recv().unwrap();
+    assert_eq!(sta, sta_thread());
+    assert_ne!(sta, mta);
+    assert_ne!(sta, thread::current().id());
+}
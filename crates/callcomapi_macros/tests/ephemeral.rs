@@ -0,0 +1,24 @@
+use callcomapi_macros::com_thread;
+use std::thread;
+
+mod common;
+
+#[com_thread(ephemeral)]
+fn isolated_query() -> thread::ThreadId {
+    common::call_com_api().unwrap();
+    thread::current().id()
+}
+
+#[com_thread(MTA, ephemeral)]
+fn isolated_mta(x: u32) -> u32 {
+    x + 1
+}
+
+#[test]
+fn test_ephemeral_functions_use_fresh_threads() {
+    let first = isolated_query();
+    let second = isolated_query();
+    assert_ne!(first, second);
+    assert_ne!(first, thread::current().id());
+    assert_eq!(isolated_mta(1), 2);
+}
@@ -0,0 +1,40 @@
+use callcomapi::{ComRuntime, inline_guard};
+use callcomapi_macros::com_thread;
+use std::sync::LazyLock;
+use std::thread;
+
+static RUNTIME: LazyLock<ComRuntime> = LazyLock::new(|| {
+    let rt = ComRuntime::new();
+    rt.set_inline(true);
+    rt
+});
+
+#[com_thread]
+fn sta_thread() -> thread::ThreadId {
+    thread::current().id()
+}
+
+#[com_thread(MTA)]
+async fn mta_thread() -> thread::ThreadId {
+    thread::current().id()
+}
+
+#[com_thread(runtime = RUNTIME)]
+fn checked_div(a: i32, b: i32) -> i32 {
+    a / b
+}
+
+#[test]
+fn test_guard_runs_macro_functions_inline() {
+    let _guard = inline_guard();
+    let caller = thread::current().id();
+    assert_eq!(sta_thread(), caller);
+    assert_eq!(futures::executor::block_on(mta_thread()), caller);
+}
+
+#[test]
+fn test_inline_runtime_propagates_panics() {
+    assert_eq!(checked_div(6, 3), 2);
+    assert!(std::panic::catch_unwind(|| checked_div(1, 0)).is_err());
+    assert_eq!(RUNTIME.stats().apartments[0].tasks_completed, 1);
+}
@@ -0,0 +1,23 @@
+use callcomapi_macros::com_thread;
+use std::thread;
+
+#[com_thread(MTA, key = host)]
+fn query_host(host: String, n: u32) -> (String, u32, thread::ThreadId) {
+    (host, n * 2, thread::current().id())
+}
+
+#[com_thread(key = (kind, id))]
+fn touch(kind: &'static str, id: u32) -> u32 {
+    let _ = kind;
+    id
+}
+
+#[test]
+fn test_keyed_functions_run_on_the_key_worker() {
+    let (host, doubled, first) = query_host("wmi-01".to_string(), 2);
+    assert_eq!((host.as_str(), doubled), ("wmi-01", 4));
+    let (_, _, second) = query_host("wmi-01".to_string(), 3);
+    assert_eq!(first, second);
+    assert_ne!(first, thread::current().id());
+    assert_eq!(touch("file", 9), 9);
+}
@@ -0,0 +1,39 @@
+use callcomapi_macros::com_thread;
+use std::sync::atomic::{AtomicU32, Ordering};
+use windows::core::{Error, HRESULT, Result};
+
+const RPC_E_SERVERCALL_RETRYLATER: HRESULT = HRESULT(0x8001_010A_u32 as i32);
+const E_ACCESSDENIED: HRESULT = HRESULT(0x8007_0005_u32 as i32);
+
+static BUSY_CALLS: AtomicU32 = AtomicU32::new(0);
+static DENIED_CALLS: AtomicU32 = AtomicU32::new(0);
+
+#[com_thread(MTA, retry(max = 3, backoff = "exp", delay = "5ms"))]
+fn busy_server(name: String) -> Result<String> {
+    if BUSY_CALLS.fetch_add(1, Ordering::SeqCst) < 2 {
+        return Err(Error::from(RPC_E_SERVERCALL_RETRYLATER));
+    }
+    Ok(name)
+}
+
+fn denied_is_transient(code: HRESULT) -> bool {
+    code == E_ACCESSDENIED
+}
+
+#[com_thread(retry(max = 1, backoff = "none", classify = denied_is_transient))]
+async fn denied_once(mut n: u32) -> Result<u32> {
+    n += 1;
+    if DENIED_CALLS.fetch_add(1, Ordering::SeqCst) == 0 {
+        return Err(Error::from(E_ACCESSDENIED));
+    }
+    Ok(n)
+}
+
+#[test]
+fn test_retried_functions_run_again_on_transient_failures() {
+    assert_eq!(busy_server("wmi".to_string()).unwrap(), "wmi");
+    assert_eq!(BUSY_CALLS.load(Ordering::SeqCst), 3);
+
+    assert_eq!(futures::executor::block_on(denied_once(1)).unwrap(), 2);
+    assert_eq!(DENIED_CALLS.load(Ordering::SeqCst), 2);
+}
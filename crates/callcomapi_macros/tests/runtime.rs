@@ -0,0 +1,41 @@
+use callcomapi::ComRuntime;
+use callcomapi_macros::com_thread;
+use std::sync::LazyLock;
+use std::thread;
+
+mod common;
+
+static RUNTIME: LazyLock<ComRuntime> = LazyLock::new(ComRuntime::new);
+
+#[com_thread(runtime = RUNTIME)]
+fn own_sta() -> thread::ThreadId {
+    common::call_com_api().unwrap();
+    thread::current().id()
+}
+
+#[com_thread(MTA, runtime = RUNTIME)]
+async fn own_mta() -> thread::ThreadId {
+    thread::current().id()
+}
+
+#[com_thread]
+fn global_sta() -> thread::ThreadId {
+    thread::current().id()
+}
+
+#[tokio::test]
+async fn test_functions_run_on_their_runtime() {
+    let own = own_sta();
+    assert_eq!(own, own_sta());
+    assert_ne!(own, global_sta());
+    assert_ne!(own, own_mta().await);
+
+    let stats = RUNTIME.stats();
+    assert!(
+        stats
+            .apartment(callcomapi::ComModel::STA)
+            .unwrap()
+            .tasks_completed
+            >= 2
+    );
+}
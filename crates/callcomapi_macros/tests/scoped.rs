@@ -0,0 +1,37 @@
+use callcomapi_macros::com_thread;
+use std::thread;
+
+mod common;
+
+struct Config {
+    prefix: String,
+    limit: usize,
+}
+
+#[com_thread(scoped)]
+fn describe(name: &str, data: &[u8], config: &Config) -> String {
+    common::call_com_api().unwrap();
+    let shown = data.len().min(config.limit);
+    format!("{}{name}:{shown}", config.prefix)
+}
+
+#[com_thread(MTA, scoped)]
+fn first_word<'a>(text: &'a str) -> (String, thread::ThreadId) {
+    let word = text.split_whitespace().next().unwrap_or_default();
+    (word.to_owned(), thread::current().id())
+}
+
+#[test]
+fn test_scoped_functions_take_borrowed_parameters() {
+    let config = Config {
+        prefix: "cpu-".into(),
+        limit: 2,
+    };
+    let name = String::from("intel");
+    assert_eq!(describe(&name, &[1, 2, 3], &config), "cpu-intel:2");
+
+    let text = String::from("hello scoped world");
+    let (word, tid) = first_word(&text);
+    assert_eq!(word, "hello");
+    assert_ne!(tid, thread::current().id());
+}
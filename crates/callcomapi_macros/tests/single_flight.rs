@@ -0,0 +1,37 @@
+use callcomapi_macros::com_thread;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+#[com_thread(MTA, single_flight)]
+fn slow_query(class: String, limit: u32) -> Vec<String> {
+    RUNS.fetch_add(1, Ordering::SeqCst);
+    thread::sleep(Duration::from_millis(50));
+    vec![format!("{class}:{limit}")]
+}
+
+#[com_thread(single_flight(key = path.to_lowercase()))]
+fn file_size(path: &'static str) -> u64 {
+    path.len() as u64
+}
+
+#[com_thread(single_flight)]
+async fn async_query(id: u32) -> u32 {
+    id + 1
+}
+
+#[test]
+fn test_single_flight_functions_share_one_run() {
+    let callers: Vec<_> = (0..4)
+        .map(|_| thread::spawn(|| slow_query("Win32_Process".to_string(), 10)))
+        .collect();
+    for caller in callers {
+        assert_eq!(caller.join().unwrap(), ["Win32_Process:10"]);
+    }
+    assert!(RUNS.load(Ordering::SeqCst) < 4);
+
+    assert_eq!(file_size("C:\\Boot.ini"), 11);
+    assert_eq!(futures::executor::block_on(async_query(1)), 2);
+}
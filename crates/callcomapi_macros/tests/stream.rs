@@ -0,0 +1,38 @@
+use callcomapi_macros::com_thread;
+use futures::{Stream, StreamExt};
+use std::thread;
+
+mod common;
+
+#[com_thread]
+fn numbers(n: u32) -> impl Stream<Item = (u32, thread::ThreadId)> {
+    common::call_com_api().unwrap();
+    (0..n).map(|i| (i, thread::current().id()))
+}
+
+#[com_thread(MTA)]
+fn endless() -> impl Stream<Item = u64> {
+    // only stops because the caller drops the stream
+    0u64..
+}
+
+#[tokio::test]
+async fn test_com_thread_stream() {
+    let items: Vec<_> = numbers(5).collect().await;
+    assert_eq!(
+        items.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+        [0, 1, 2, 3, 4]
+    );
+    assert!(items.iter().all(|(_, tid)| *tid == items[0].1));
+    assert_ne!(items[0].1, thread::current().id());
+}
+
+#[tokio::test]
+async fn test_com_thread_stream_cancelled_on_drop() {
+    let first: Vec<_> = endless().take(3).collect().await;
+    assert_eq!(first, [0, 1, 2]);
+
+    // the producer stopped, so the apartment is free for the next call
+    let again: Vec<_> = endless().take(2).collect().await;
+    assert_eq!(again, [0, 1]);
+}
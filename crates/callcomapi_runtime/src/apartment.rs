@@ -0,0 +1,724 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::time::{Duration, Instant};
+
+use crate::config::{ApartmentConfig, RecyclePolicy};
+use crate::events::{RuntimeEvent, emit};
+use crate::runtime::Shared;
+use crate::{ApartmentExecutor, CallError, ComGuard, ComModel, Message};
+
+/// Lifecycle of an apartment as reported in stats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApartmentState {
+    Running,
+    /// The worker died and a replacement is waiting out its backoff.
+    Restarting,
+    /// The restart policy was exhausted; new work is rejected.
+    Failed,
+    /// The runtime was shut down; queued work drains, new work is rejected.
+    ShutDown,
+}
+
+/// The task a worker is currently executing.
+#[derive(Clone)]
+pub(crate) struct RunningTask {
+    pub(crate) label: &'static str,
+    pub(crate) started: Instant,
+    /// Set once the watchdog has reported this task as hung.
+    pub(crate) flagged: bool,
+    /// Cleared for sessions and streams, which may run as long as their
+    /// caller likes.
+    pub(crate) watched: bool,
+}
+
+/// Per-worker bookkeeping shared between the worker thread and observers.
+#[derive(Default)]
+pub(crate) struct WorkerSlot {
+    running: Mutex<Option<RunningTask>>,
+}
+
+impl WorkerSlot {
+    pub(crate) fn running(&self) -> Option<RunningTask> {
+        self.running.lock().unwrap().clone()
+    }
+
+    /// Mark the running task as hung. Returns the task the first time only,
+    /// and never for an unwatched one.
+    pub(crate) fn flag_if_older_than(&self, threshold: Duration) -> Option<RunningTask> {
+        let mut running = self.running.lock().unwrap();
+        match running.as_mut() {
+            Some(task) if task.watched && !task.flagged && task.started.elapsed() >= threshold => {
+                task.flagged = true;
+                Some(task.clone())
+            }
+            _ => None,
+        }
+    }
+
+    fn begin(&self, msg: &Message) {
+        *self.running.lock().unwrap() = Some(RunningTask {
+            label: msg.label(),
+            started: Instant::now(),
+            flagged: false,
+            watched: msg.watched(),
+        });
+    }
+
+    fn finish(&self) {
+        *self.running.lock().unwrap() = None;
+    }
+
+    fn resume(&self, task: RunningTask) {
+        *self.running.lock().unwrap() = Some(task);
+    }
+}
+
+/// Index of the apartment's own queue in [`Lifecycle::lanes`]; keyed calls
+/// use the lanes after it.
+pub(crate) const MAIN_LANE: usize = 0;
+
+/// A task queue and the worker currently serving it.
+struct Lane {
+    receiver: Arc<Mutex<mpsc::Receiver<Message>>>,
+    /// Dropped on shutdown so workers see the queue disconnect once drained.
+    sender: Option<mpsc::Sender<Message>>,
+    /// Bumped whenever a new worker takes over; older workers exit.
+    generation: u64,
+    /// Workers start with the first queued message, so apartments used only
+    /// inline never spawn a thread.
+    started: bool,
+    slot: Arc<WorkerSlot>,
+}
+
+impl Lane {
+    fn new() -> Self {
+        let (sender, receiver) = mpsc::channel::<Message>();
+        Lane {
+            receiver: Arc::new(Mutex::new(receiver)),
+            sender: Some(sender),
+            generation: 0,
+            started: false,
+            slot: Arc::new(WorkerSlot::default()),
+        }
+    }
+
+    /// Hand the lane over to a new worker generation.
+    fn advance(&mut self) {
+        self.generation += 1;
+        self.slot = Arc::new(WorkerSlot::default());
+    }
+}
+
+struct Lifecycle {
+    state: ApartmentState,
+    /// The apartment's own lane, then one per keyed worker.
+    lanes: Vec<Lane>,
+    config: ApartmentConfig,
+    restart_history: VecDeque<Instant>,
+    /// Set while an existing thread runs this apartment's tasks instead of
+    /// spawned workers.
+    host: Option<Arc<dyn ApartmentExecutor>>,
+}
+
+/// One COM apartment: its task queues, each served by one worker thread at
+/// a time, plus counters.
+pub(crate) struct Apartment {
+    pub(crate) model: ComModel,
+    lifecycle: Mutex<Lifecycle>,
+    consecutive_crashes: AtomicU32,
+    pub(crate) queued: AtomicUsize,
+    pub(crate) completed: AtomicU64,
+    pub(crate) hung: AtomicU64,
+    pub(crate) replacements: AtomicU64,
+    pub(crate) restarts: AtomicU64,
+    pub(crate) recycles: AtomicU64,
+    #[cfg(feature = "fault-injection")]
+    pub(crate) faults: Arc<crate::faults::Faults>,
+    pub(crate) spares: crate::ephemeral::Spares,
+    pub(crate) timers: crate::timer::Timers,
+    pub(crate) circuit: Arc<crate::circuit::Circuit>,
+}
+
+impl Apartment {
+    #[cfg_attr(not(feature = "fault-injection"), allow(unused_variables))]
+    pub(crate) fn start(model: ComModel, runtime: &Shared) -> Arc<Self> {
+        Arc::new(Apartment {
+            model,
+            lifecycle: Mutex::new(Lifecycle {
+                state: ApartmentState::Running,
+                lanes: vec![Lane::new()],
+                config: ApartmentConfig::default(),
+                restart_history: VecDeque::new(),
+                host: None,
+            }),
+            consecutive_crashes: AtomicU32::new(0),
+            queued: AtomicUsize::new(0),
+            completed: AtomicU64::new(0),
+            hung: AtomicU64::new(0),
+            replacements: AtomicU64::new(0),
+            restarts: AtomicU64::new(0),
+            recycles: AtomicU64::new(0),
+            #[cfg(feature = "fault-injection")]
+            faults: runtime.faults.clone(),
+            spares: Default::default(),
+            timers: Default::default(),
+            circuit: Arc::new(crate::circuit::Circuit::new(None)),
+        })
+    }
+
+    pub(crate) fn set_config(self: &Arc<Self>, config: ApartmentConfig) {
+        self.circuit.configure(config.circuit);
+        let mut lifecycle = self.lifecycle.lock().unwrap();
+        // dropped keyed lanes drain their queues, see `lane_policy`
+        lifecycle.lanes.truncate(1 + config.keyed_workers);
+        lifecycle.config = config;
+        drop(lifecycle);
+        crate::ephemeral::refill(self);
+    }
+
+    pub(crate) fn config(&self) -> ApartmentConfig {
+        self.lifecycle.lock().unwrap().config.clone()
+    }
+
+    pub(crate) fn state(&self) -> ApartmentState {
+        self.lifecycle.lock().unwrap().state
+    }
+
+    /// Queue a message for whichever worker is current.
+    pub(crate) fn send(self: &Arc<Self>, msg: Message) -> Result<(), CallError> {
+        self.send_to(self.lifecycle.lock().unwrap(), MAIN_LANE, msg)
+    }
+
+    /// Queue a keyed message on the lane serving `hash`, or on the
+    /// apartment's own lane without keyed workers.
+    pub(crate) fn send_keyed(self: &Arc<Self>, hash: u64, msg: Message) -> Result<(), CallError> {
+        let mut lifecycle = self.lifecycle.lock().unwrap();
+        let workers = lifecycle.config.keyed_workers;
+        // lanes added after shutdown would accept work nobody waits for
+        if workers == 0 || lifecycle.lanes[MAIN_LANE].sender.is_none() {
+            return self.send_to(lifecycle, MAIN_LANE, msg);
+        }
+        while lifecycle.lanes.len() <= workers {
+            lifecycle.lanes.push(Lane::new());
+        }
+        let lane = 1 + (hash % workers as u64) as usize;
+        self.send_to(lifecycle, lane, msg)
+    }
+
+    // called under the lock so a concurrent failure cannot strand the message
+    fn send_to(
+        self: &Arc<Self>,
+        mut lifecycle: MutexGuard<'_, Lifecycle>,
+        lane: usize,
+        msg: Message,
+    ) -> Result<(), CallError> {
+        if lifecycle.state == ApartmentState::Failed {
+            return Err(CallError::ApartmentFailed(self.model));
+        }
+        let hosted = lane == MAIN_LANE && lifecycle.host.is_some();
+        let queue = &mut lifecycle.lanes[lane];
+        if queue.sender.is_none() {
+            return Err(CallError::ShutDown(self.model));
+        }
+        if !queue.started && !hosted {
+            queue.started = true;
+            self.spawn_worker(lane, queue, Duration::ZERO);
+        }
+        let sender = lifecycle.lanes[lane]
+            .sender
+            .as_ref()
+            .expect("checked above");
+        #[cfg(feature = "tokio")]
+        let msg = match tokio::runtime::Handle::try_current() {
+            Ok(handle) if lifecycle.config.tokio == crate::TokioMode::CallerHandle => {
+                msg.enter_handle(handle)
+            }
+            _ => msg,
+        };
+        self.queued.fetch_add(1, Ordering::Relaxed);
+        sender.send(msg).expect("apartment owns its receiver");
+        let host = lifecycle.host.clone().filter(|_| hosted);
+        drop(lifecycle);
+        if let Some(host) = host {
+            host.wake();
+        }
+        Ok(())
+    }
+
+    /// Let `host` run this apartment's tasks from its own loop, unless a
+    /// worker or another host already took the apartment.
+    pub(crate) fn attach(&self, host: Arc<dyn ApartmentExecutor>) -> Result<(), CallError> {
+        let mut lifecycle = self.lifecycle.lock().unwrap();
+        let main = &lifecycle.lanes[MAIN_LANE];
+        if main.sender.is_none() {
+            return Err(match lifecycle.state {
+                ApartmentState::Failed => CallError::ApartmentFailed(self.model),
+                _ => CallError::ShutDown(self.model),
+            });
+        }
+        if main.started || lifecycle.host.is_some() {
+            return Err(CallError::AlreadyServed(self.model));
+        }
+        lifecycle.host = Some(host);
+        Ok(())
+    }
+
+    /// Run the queued tasks on the hosting thread, returning how many ran.
+    pub(crate) fn run_hosted(&self) -> usize {
+        let (slot, receiver) = {
+            let lifecycle = self.lifecycle.lock().unwrap();
+            let main = &lifecycle.lanes[MAIN_LANE];
+            (main.slot.clone(), main.receiver.clone())
+        };
+        let mut ran = 0;
+        loop {
+            // not held while the task runs, which may queue more work
+            let msg = match self.timers.pop_due() {
+                Some(msg) => msg,
+                None => match receiver.lock().unwrap().try_recv() {
+                    Ok(Message::Wake) => {
+                        self.queued.fetch_sub(1, Ordering::Relaxed);
+                        continue;
+                    }
+                    Ok(msg) => {
+                        self.queued.fetch_sub(1, Ordering::Relaxed);
+                        msg
+                    }
+                    Err(_) => return ran,
+                },
+            };
+            let Some(msg) = self.unless_failed(msg) else {
+                continue;
+            };
+            slot.begin(&msg);
+            let panicked = msg.run();
+            slot.finish();
+            if !panicked {
+                self.completed.fetch_add(1, Ordering::Relaxed);
+            }
+            ran += 1;
+        }
+    }
+
+    /// The hosting thread is leaving: hand the queue back to a spawned
+    /// worker, started right away if tasks are left.
+    pub(crate) fn detach(self: &Arc<Self>) {
+        let mut lifecycle = self.lifecycle.lock().unwrap();
+        lifecycle.host = None;
+        let main = &mut lifecycle.lanes[MAIN_LANE];
+        if main.sender.is_none() {
+            let receiver = main.receiver.clone();
+            drop(lifecycle);
+            self.abandon_queued(&receiver, self.lost());
+            return;
+        }
+        main.advance();
+        main.started =
+            self.queued.load(Ordering::Relaxed) > 0 || self.timers.until_next().is_some();
+        if main.started {
+            self.spawn_worker(MAIN_LANE, main, Duration::ZERO);
+        }
+    }
+
+    /// Run `msg` on the calling thread, with the same bookkeeping as a worker.
+    ///
+    /// A panic is delivered through the reply like on a worker, but does not
+    /// count against the restart policy since no worker dies.
+    pub(crate) fn run_inline(&self, msg: Message) -> Result<(), CallError> {
+        let slot = {
+            let lifecycle = self.lifecycle.lock().unwrap();
+            match lifecycle.state {
+                ApartmentState::Failed => return Err(CallError::ApartmentFailed(self.model)),
+                ApartmentState::ShutDown => return Err(CallError::ShutDown(self.model)),
+                _ => lifecycle.lanes[MAIN_LANE].slot.clone(),
+            }
+        };
+
+        // tasks submitted from inside an inline task nest on the same thread
+        let previous = slot.running();
+        slot.begin(&msg);
+        let panicked = crate::inline::run(msg);
+        match previous {
+            Some(task) => slot.resume(task),
+            None => slot.finish(),
+        }
+        if !panicked {
+            self.completed.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    /// Stop accepting work. Workers exit once the queue has drained.
+    pub(crate) fn close(&self) {
+        let mut lifecycle = self.lifecycle.lock().unwrap();
+        if lifecycle.state != ApartmentState::Failed {
+            lifecycle.state = ApartmentState::ShutDown;
+        }
+        for lane in &mut lifecycle.lanes {
+            lane.sender = None;
+        }
+        drop(lifecycle);
+        self.spares.clear();
+        self.timers.close();
+    }
+
+    /// The error reported to a caller whose reply channel was dropped.
+    pub(crate) fn lost(&self) -> CallError {
+        match self.state() {
+            ApartmentState::Failed => CallError::ApartmentFailed(self.model),
+            ApartmentState::ShutDown => CallError::ShutDown(self.model),
+            _ => CallError::WorkerLost(self.model),
+        }
+    }
+
+    /// Bookkeeping of the worker serving the apartment's own lane.
+    pub(crate) fn current_slot(&self) -> Arc<WorkerSlot> {
+        self.lifecycle.lock().unwrap().lanes[MAIN_LANE].slot.clone()
+    }
+
+    /// Bookkeeping of the worker of every lane, by lane.
+    pub(crate) fn slots(&self) -> Vec<Arc<WorkerSlot>> {
+        let lifecycle = self.lifecycle.lock().unwrap();
+        lifecycle.lanes.iter().map(|l| l.slot.clone()).collect()
+    }
+
+    /// Route new work on `lane` to a freshly initialized worker.
+    ///
+    /// The previous worker finishes whatever it is running and then exits;
+    /// queued tasks are picked up by the new worker.
+    pub(crate) fn replace_worker(self: &Arc<Self>, lane: usize) {
+        let mut lifecycle = self.lifecycle.lock().unwrap();
+        // a hosting thread cannot be replaced
+        if lifecycle.state == ApartmentState::Failed
+            || (lane == MAIN_LANE && lifecycle.host.is_some())
+        {
+            return;
+        }
+        let Some(queue) = lifecycle.lanes.get_mut(lane) else {
+            return;
+        };
+        queue.advance();
+        queue.started = true;
+        self.spawn_worker(lane, queue, Duration::ZERO);
+        self.replacements.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The lane served by the worker of `generation` reading `receiver`, if
+    /// that worker is still current.
+    fn current_lane<'a>(
+        lifecycle: &'a mut Lifecycle,
+        lane: usize,
+        generation: u64,
+        receiver: &Arc<Mutex<mpsc::Receiver<Message>>>,
+    ) -> Option<&'a mut Lane> {
+        lifecycle
+            .lanes
+            .get_mut(lane)
+            .filter(|l| l.generation == generation && Arc::ptr_eq(&l.receiver, receiver))
+    }
+
+    /// The recycle policy for the worker of `generation` on `lane`, or
+    /// `None` once a newer worker has taken over. Workers are not recycled
+    /// while draining, which includes the queue of a dropped keyed lane.
+    fn lane_policy(
+        &self,
+        lane: usize,
+        generation: u64,
+        receiver: &Arc<Mutex<mpsc::Receiver<Message>>>,
+    ) -> Option<RecyclePolicy> {
+        let mut lifecycle = self.lifecycle.lock().unwrap();
+        let dropped = lifecycle
+            .lanes
+            .get(lane)
+            .is_none_or(|l| !Arc::ptr_eq(&l.receiver, receiver));
+        if dropped {
+            return Some(RecyclePolicy::default());
+        }
+        Self::current_lane(&mut lifecycle, lane, generation, receiver)?;
+        Some(match lifecycle.state {
+            ApartmentState::ShutDown => RecyclePolicy::default(),
+            _ => lifecycle.config.recycle,
+        })
+    }
+
+    /// Hand `lane` over to a fresh worker once worker `generation` has
+    /// reached its recycle limit. The caller exits afterwards, uninitializing
+    /// COM on its own thread.
+    fn retire(
+        self: &Arc<Self>,
+        lane: usize,
+        generation: u64,
+        receiver: &Arc<Mutex<mpsc::Receiver<Message>>>,
+        tasks: u64,
+        age: Duration,
+    ) {
+        let mut lifecycle = self.lifecycle.lock().unwrap();
+        if lifecycle.state == ApartmentState::Failed {
+            return;
+        }
+        let Some(queue) = Self::current_lane(&mut lifecycle, lane, generation, receiver) else {
+            return;
+        };
+        queue.advance();
+        self.spawn_worker(lane, queue, Duration::ZERO);
+        drop(lifecycle);
+
+        self.recycles.fetch_add(1, Ordering::Relaxed);
+        emit(RuntimeEvent::WorkerRecycled {
+            model: self.model,
+            tasks,
+            age,
+        });
+    }
+
+    fn mark_running(&self, lane: usize, generation: u64) {
+        let mut lifecycle = self.lifecycle.lock().unwrap();
+        let current = lifecycle
+            .lanes
+            .get(lane)
+            .is_some_and(|l| l.generation == generation);
+        if current && lifecycle.state == ApartmentState::Restarting {
+            lifecycle.state = ApartmentState::Running;
+        }
+    }
+
+    /// Apply the restart policy after the worker of `generation` on `lane`
+    /// died.
+    fn worker_crashed(
+        self: &Arc<Self>,
+        lane: usize,
+        generation: u64,
+        receiver: &Arc<Mutex<mpsc::Receiver<Message>>>,
+    ) {
+        let mut lifecycle = self.lifecycle.lock().unwrap();
+        if lifecycle.state == ApartmentState::Failed
+            || Self::current_lane(&mut lifecycle, lane, generation, receiver).is_none()
+        {
+            return;
+        }
+
+        // a runtime that is shutting down does not restart its workers;
+        // the other lanes drain their own queues
+        if lifecycle.state == ApartmentState::ShutDown {
+            drop(lifecycle);
+            self.abandon_queued(receiver, CallError::ShutDown(self.model));
+            return;
+        }
+
+        let policy = lifecycle.config.restart;
+        if !policy.admit(&mut lifecycle.restart_history, Instant::now()) {
+            lifecycle.state = ApartmentState::Failed;
+            // workers of the other lanes may be blocked receiving; the
+            // disconnect wakes them to abandon what is left, see
+            // `unless_failed`
+            for lane in &mut lifecycle.lanes {
+                lane.sender = None;
+            }
+            drop(lifecycle);
+            self.abandon_queued(receiver, CallError::ApartmentFailed(self.model));
+            emit(RuntimeEvent::ApartmentFailed { model: self.model });
+            return;
+        }
+
+        let consecutive = self.consecutive_crashes.fetch_add(1, Ordering::Relaxed) + 1;
+        let backoff = policy.backoff_for(consecutive);
+        lifecycle.state = ApartmentState::Restarting;
+        let queue = &mut lifecycle.lanes[lane];
+        queue.advance();
+        self.spawn_worker(lane, queue, backoff);
+        drop(lifecycle);
+
+        let restarts = self.restarts.fetch_add(1, Ordering::Relaxed) + 1;
+        emit(RuntimeEvent::WorkerRestarted {
+            model: self.model,
+            restarts,
+            backoff,
+        });
+    }
+
+    /// Drop every message queued on a lane no worker serves any more; this
+    /// closes their reply channels.
+    fn abandon_queued(&self, receiver: &Mutex<mpsc::Receiver<Message>>, error: CallError) {
+        let receiver = receiver.lock().unwrap();
+        while let Ok(msg) = receiver.try_recv() {
+            self.queued.fetch_sub(1, Ordering::Relaxed);
+            msg.abandon(error);
+        }
+    }
+
+    /// `msg`, unless the apartment has failed since it was queued, in which
+    /// case it is abandoned.
+    fn unless_failed(&self, msg: Message) -> Option<Message> {
+        if self.state() != ApartmentState::Failed {
+            return Some(msg);
+        }
+        msg.abandon(CallError::ApartmentFailed(self.model));
+        None
+    }
+
+    /// Start a worker for `queue`, the lane at index `lane`, at its current
+    /// generation.
+    fn spawn_worker(self: &Arc<Self>, lane: usize, queue: &Lane, delay: Duration) {
+        let apartment = Arc::downgrade(self);
+        let receiver = queue.receiver.clone();
+        let generation = queue.generation;
+        let slot = queue.slot.clone();
+        let model = self.model;
+
+        // spawn background thread
+        std::thread::spawn(move || {
+            std::thread::sleep(delay);
+            let mut exit = WorkerExit {
+                apartment: apartment.clone(),
+                lane,
+                generation,
+                receiver: receiver.clone(),
+                crashed: true,
+            };
+
+            #[cfg(feature = "fault-injection")]
+            if apartment
+                .upgrade()
+                .is_some_and(|a| a.faults.init_fails(model))
+            {
+                return;
+            }
+            let hr = unsafe { windows::Win32::System::Com::CoInitializeEx(None, model.coinit()) };
+            if hr.is_err() {
+                return;
+            }
+            let _guard = ComGuard::entered(model);
+            crate::current::mark_worker();
+
+            match apartment.upgrade() {
+                Some(apartment) => apartment.mark_running(lane, generation),
+                None => return,
+            }
+
+            #[cfg(feature = "tokio")]
+            let tokio_rt = {
+                let mode = match apartment.upgrade() {
+                    Some(apartment) => apartment.lifecycle.lock().unwrap().config.tokio,
+                    None => return,
+                };
+                crate::tokio_rt::WorkerRuntime::install(mode)
+            };
+            #[cfg(feature = "tokio")]
+            let _tokio_context = tokio_rt.enter();
+
+            let started = Instant::now();
+            let mut tasks = 0u64;
+            // stops once a newer worker has taken over this apartment
+            while let Some(recycle) = apartment
+                .upgrade()
+                .and_then(|a| a.lane_policy(lane, generation, &receiver))
+            {
+                if recycle.is_due(tasks, started.elapsed()) {
+                    if let Some(apartment) = apartment.upgrade() {
+                        apartment.retire(lane, generation, &receiver, tasks, started.elapsed());
+                    }
+                    break;
+                }
+
+                // due timers go first; otherwise wait for work or the next
+                // deadline. Timers belong to the apartment's own lane.
+                let (timer, next_timer) = match apartment.upgrade() {
+                    Some(_) if lane != MAIN_LANE => (None, None),
+                    Some(apartment) => match apartment.timers.pop_due() {
+                        Some(msg) => (Some(msg), None),
+                        None => (None, apartment.timers.until_next()),
+                    },
+                    None => break,
+                };
+                let msg = match timer {
+                    Some(msg) => msg,
+                    None => {
+                        let timeout = recycle
+                            .remaining_age(started.elapsed())
+                            .into_iter()
+                            .chain(next_timer)
+                            .min();
+                        let msg = match timeout {
+                            Some(timeout) => receiver.lock().unwrap().recv_timeout(timeout),
+                            None => receiver
+                                .lock()
+                                .unwrap()
+                                .recv()
+                                .map_err(|_| RecvTimeoutError::Disconnected),
+                        };
+                        match msg {
+                            Ok(msg) => {
+                                if let Some(apartment) = apartment.upgrade() {
+                                    apartment.queued.fetch_sub(1, Ordering::Relaxed);
+                                }
+                                msg
+                            }
+                            Err(RecvTimeoutError::Timeout) => continue,
+                            Err(RecvTimeoutError::Disconnected) => break,
+                        }
+                    }
+                };
+                if let Message::Wake = msg {
+                    continue;
+                }
+                // the runtime owning this apartment has been dropped
+                let Some(apartment) = apartment.upgrade() else {
+                    msg.abandon(CallError::ShutDown(model));
+                    break;
+                };
+                let Some(msg) = apartment.unless_failed(msg) else {
+                    continue;
+                };
+
+                #[cfg(feature = "fault-injection")]
+                if apartment.faults.worker_dies(model) {
+                    msg.abandon(CallError::WorkerLost(model));
+                    return;
+                }
+                // the original reply sender outlives the task, see `Message::Sync`
+                #[cfg(feature = "fault-injection")]
+                let (msg, _lost_reply) = match apartment.faults.drop_result(model) {
+                    true => msg.divert_reply(),
+                    false => (msg, None),
+                };
+
+                slot.begin(&msg);
+                #[cfg(feature = "fault-injection")]
+                if let Some(delay) = apartment.faults.delay(model) {
+                    std::thread::sleep(delay);
+                }
+                let panicked = msg.run();
+                slot.finish();
+
+                if panicked {
+                    return;
+                }
+                tasks += 1;
+                apartment.completed.fetch_add(1, Ordering::Relaxed);
+                apartment.consecutive_crashes.store(0, Ordering::Relaxed);
+            }
+            exit.crashed = false;
+        });
+    }
+}
+
+/// Reports a worker that leaves its loop abnormally, including by unwinding.
+struct WorkerExit {
+    apartment: Weak<Apartment>,
+    lane: usize,
+    generation: u64,
+    receiver: Arc<Mutex<mpsc::Receiver<Message>>>,
+    crashed: bool,
+}
+
+impl Drop for WorkerExit {
+    fn drop(&mut self) {
+        if self.crashed
+            && let Some(apartment) = self.apartment.upgrade()
+        {
+            apartment.worker_crashed(self.lane, self.generation, &self.receiver);
+        }
+    }
+}
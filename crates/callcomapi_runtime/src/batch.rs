@@ -0,0 +1,64 @@
+use std::any::Any;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+use crate::{ComModel, ComRuntime};
+
+/// Run every closure in `tasks` back to back on the apartment for `model`,
+/// sending the whole batch as a single message.
+///
+/// Results are returned in submission order. A panic in one item is caught
+/// and returned as that item's `Err`; it does not affect the other items or
+/// restart the worker.
+pub fn call_batch<F, R>(model: ComModel, tasks: Vec<F>) -> Vec<std::thread::Result<R>>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    ComRuntime::global().call_batch(model, tasks)
+}
+
+/// Async version of [`call_batch`].
+pub fn call_batch_async<F, R>(
+    model: ComModel,
+    tasks: Vec<F>,
+) -> impl std::future::Future<Output = Vec<std::thread::Result<R>>>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    ComRuntime::global().call_batch_async(model, tasks)
+}
+
+impl ComRuntime {
+    /// See [`call_batch`].
+    pub fn call_batch<F, R>(&self, model: ComModel, tasks: Vec<F>) -> Vec<std::thread::Result<R>>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Any + Send + 'static,
+    {
+        self.call_sync_labeled(model, std::any::type_name::<F>(), move || run_batch(tasks))
+    }
+
+    /// See [`call_batch_async`].
+    pub fn call_batch_async<F, R>(
+        &self,
+        model: ComModel,
+        tasks: Vec<F>,
+    ) -> impl std::future::Future<Output = Vec<std::thread::Result<R>>> + use<F, R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Any + Send + 'static,
+    {
+        self.call_async_labeled(model, std::any::type_name::<F>(), move || run_batch(tasks))
+    }
+}
+
+fn run_batch<F, R>(tasks: Vec<F>) -> Vec<std::thread::Result<R>>
+where
+    F: FnOnce() -> R,
+{
+    tasks
+        .into_iter()
+        .map(|f| catch_unwind(AssertUnwindSafe(f)))
+        .collect()
+}
@@ -0,0 +1,307 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, DefaultHasher, Hash};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::stats::CacheStats;
+use crate::{ComModel, ComRuntime};
+
+struct Entry<R> {
+    value: R,
+    expires: Instant,
+}
+
+struct Entries<K, R> {
+    // a fixed hasher, so the map can be built in a `const fn`
+    map: HashMap<K, Entry<R>, BuildHasherDefault<DefaultHasher>>,
+    /// Bumped by every invalidation, so a call that started before it does
+    /// not store its now stale result.
+    generation: u64,
+}
+
+/// Results of calls by key, each kept for a fixed time.
+///
+/// Backs `#[com_thread(cache(...))]`, which declares one per function.
+/// Once `capacity` results are stored, adding another evicts expired
+/// results, or else the oldest one.
+///
+/// ```ignore
+/// static PROCESSORS: ResultCache<String, Vec<String>> =
+///     ResultCache::new("processors", Duration::from_secs(60), 16);
+///
+/// let names = call_cached(ComModel::MTA, &PROCESSORS, host.clone(), || query_processors());
+/// PROCESSORS.invalidate(&host);
+/// ```
+pub struct ResultCache<K, R> {
+    label: &'static str,
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<Entries<K, R>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<K, R> ResultCache<K, R> {
+    /// An empty cache reported as `label` in runtime stats.
+    pub const fn new(label: &'static str, ttl: Duration, capacity: usize) -> Self {
+        ResultCache {
+            label,
+            ttl,
+            capacity,
+            entries: Mutex::new(Entries {
+                map: HashMap::with_hasher(BuildHasherDefault::new()),
+                generation: 0,
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Drop every stored result.
+    pub fn invalidate_all(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.map.clear();
+        entries.generation += 1;
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            label: self.label,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().unwrap().map.len(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, R> ResultCache<K, R> {
+    /// Drop the result stored for `key`.
+    pub fn invalidate(&self, key: &K) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.map.remove(key);
+        entries.generation += 1;
+    }
+}
+
+impl<K: Eq + Hash + Clone, R: Clone> ResultCache<K, R> {
+    /// The unexpired result for `key`, or the generation a result computed
+    /// now must be stored under.
+    fn lookup(&self, key: &K) -> Result<R, u64> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.map.get(key) {
+            Some(entry) if entry.expires > Instant::now() => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(entry.value.clone());
+            }
+            Some(_) => {
+                entries.map.remove(key);
+            }
+            None => {}
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        Err(entries.generation)
+    }
+
+    fn store(&self, key: K, generation: u64, value: R) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.generation != generation || self.capacity == 0 {
+            return;
+        }
+        let now = Instant::now();
+        if entries.map.len() >= self.capacity && !entries.map.contains_key(&key) {
+            entries.map.retain(|_, entry| entry.expires > now);
+            if entries.map.len() >= self.capacity {
+                let oldest = entries.map.iter().min_by_key(|(_, entry)| entry.expires);
+                if let Some(oldest) = oldest.map(|(key, _)| key.clone()) {
+                    entries.map.remove(&oldest);
+                }
+            }
+        }
+        entries.map.insert(
+            key,
+            Entry {
+                value,
+                expires: now + self.ttl,
+            },
+        );
+    }
+}
+
+trait CacheSource: Send + Sync {
+    fn stats(&self) -> CacheStats;
+}
+
+impl<K: Send, R: Send> CacheSource for ResultCache<K, R> {
+    fn stats(&self) -> CacheStats {
+        ResultCache::stats(self)
+    }
+}
+
+/// Caches used through a runtime, listed in its stats.
+#[derive(Default)]
+pub(crate) struct Caches(Mutex<Vec<&'static dyn CacheSource>>);
+
+impl Caches {
+    fn register(&self, cache: &'static dyn CacheSource) {
+        let mut caches = self.0.lock().unwrap();
+        if !caches.iter().any(|c| std::ptr::addr_eq(*c, cache)) {
+            caches.push(cache);
+        }
+    }
+
+    pub(crate) fn stats(&self) -> Vec<CacheStats> {
+        self.0.lock().unwrap().iter().map(|c| c.stats()).collect()
+    }
+}
+
+/// Return the result `cache` holds for `key`, or run `f` on the apartment
+/// for `model` and store its result.
+///
+/// Concurrent misses for the same key each run `f`. A panic in `f` is
+/// re-raised and nothing is stored.
+pub fn call_cached<K, F, R>(model: ComModel, cache: &'static ResultCache<K, R>, key: K, f: F) -> R
+where
+    K: Eq + Hash + Clone + Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Clone + Send + 'static,
+{
+    ComRuntime::global().call_cached(model, cache, key, f)
+}
+
+/// Like [`call_cached`], with a label identifying the task in stats and
+/// watchdog reports.
+pub fn call_cached_labeled<K, F, R>(
+    model: ComModel,
+    cache: &'static ResultCache<K, R>,
+    key: K,
+    label: &'static str,
+    f: F,
+) -> R
+where
+    K: Eq + Hash + Clone + Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Clone + Send + 'static,
+{
+    ComRuntime::global().call_cached_labeled(model, cache, key, label, f)
+}
+
+/// Async version of [`call_cached`].
+pub fn call_cached_async<K, F, R>(
+    model: ComModel,
+    cache: &'static ResultCache<K, R>,
+    key: K,
+    f: F,
+) -> impl Future<Output = R> + use<K, F, R>
+where
+    K: Eq + Hash + Clone + Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Clone + Send + 'static,
+{
+    ComRuntime::global().call_cached_async(model, cache, key, f)
+}
+
+/// Async version of [`call_cached_labeled`].
+pub fn call_cached_async_labeled<K, F, R>(
+    model: ComModel,
+    cache: &'static ResultCache<K, R>,
+    key: K,
+    label: &'static str,
+    f: F,
+) -> impl Future<Output = R> + use<K, F, R>
+where
+    K: Eq + Hash + Clone + Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Clone + Send + 'static,
+{
+    ComRuntime::global().call_cached_async_labeled(model, cache, key, label, f)
+}
+
+impl ComRuntime {
+    /// See [`call_cached`].
+    pub fn call_cached<K, F, R>(
+        &self,
+        model: ComModel,
+        cache: &'static ResultCache<K, R>,
+        key: K,
+        f: F,
+    ) -> R
+    where
+        K: Eq + Hash + Clone + Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+        R: Any + Clone + Send + 'static,
+    {
+        self.call_cached_labeled(model, cache, key, std::any::type_name::<F>(), f)
+    }
+
+    /// See [`call_cached_labeled`].
+    pub fn call_cached_labeled<K, F, R>(
+        &self,
+        model: ComModel,
+        cache: &'static ResultCache<K, R>,
+        key: K,
+        label: &'static str,
+        f: F,
+    ) -> R
+    where
+        K: Eq + Hash + Clone + Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+        R: Any + Clone + Send + 'static,
+    {
+        let generation = match cache.lookup(&key) {
+            Ok(hit) => return hit,
+            Err(generation) => generation,
+        };
+        self.shared.caches.register(cache);
+        let r = self.call_sync_labeled(model, label, f);
+        cache.store(key, generation, r.clone());
+        r
+    }
+
+    /// See [`call_cached_async`].
+    pub fn call_cached_async<K, F, R>(
+        &self,
+        model: ComModel,
+        cache: &'static ResultCache<K, R>,
+        key: K,
+        f: F,
+    ) -> impl Future<Output = R> + use<K, F, R>
+    where
+        K: Eq + Hash + Clone + Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+        R: Any + Clone + Send + 'static,
+    {
+        self.call_cached_async_labeled(model, cache, key, std::any::type_name::<F>(), f)
+    }
+
+    /// See [`call_cached_async_labeled`].
+    pub fn call_cached_async_labeled<K, F, R>(
+        &self,
+        model: ComModel,
+        cache: &'static ResultCache<K, R>,
+        key: K,
+        label: &'static str,
+        f: F,
+    ) -> impl Future<Output = R> + use<K, F, R>
+    where
+        K: Eq + Hash + Clone + Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+        R: Any + Clone + Send + 'static,
+    {
+        let miss = cache.lookup(&key).map_err(|generation| {
+            self.shared.caches.register(cache);
+            (generation, self.call_async_labeled(model, label, f))
+        });
+        async move {
+            match miss {
+                Ok(hit) => hit,
+                Err((generation, call)) => {
+                    let r = call.await;
+                    cache.store(key, generation, r.clone());
+                    r
+                }
+            }
+        }
+    }
+}
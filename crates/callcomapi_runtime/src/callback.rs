@@ -0,0 +1,323 @@
+use std::any::Any;
+use std::marker::PhantomData;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::{Arc, Mutex, Weak, mpsc};
+
+use crate::apartment::Apartment;
+use crate::detached::{self, DetachedError};
+use crate::{CallError, ComModel, ComRuntime, Message, Reply, Task, TaskImpl, unpack};
+
+type Job = Box<dyn FnOnce() + Send>;
+type OnDone<R> = Box<dyn FnOnce(Result<R, CallError>) + Send>;
+
+/// Somewhere completion callbacks can be posted to, such as a GUI event
+/// loop or a plugin host's work queue.
+///
+/// Implemented for closures taking the job, so any queue can be adapted:
+///
+/// ```ignore
+/// let ui = ui_queue.clone();
+/// call_with_executor(ComModel::STA, query, move |job| ui.post(job), show_result);
+/// ```
+pub trait CompletionExecutor: Send + Sync {
+    /// Arrange for `job` to run. Must not block.
+    fn post(&self, job: Box<dyn FnOnce() + Send>);
+}
+
+impl<E> CompletionExecutor for E
+where
+    E: Fn(Box<dyn FnOnce() + Send>) + Send + Sync,
+{
+    fn post(&self, job: Box<dyn FnOnce() + Send>) {
+        self(job)
+    }
+}
+
+/// The thread running completion callbacks of a runtime, started on first use.
+#[derive(Default)]
+pub(crate) struct Completions {
+    sender: Mutex<Option<mpsc::Sender<Job>>>,
+}
+
+impl Completions {
+    fn sender(&self) -> mpsc::Sender<Job> {
+        self.sender
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| {
+                let (tx, rx) = mpsc::channel::<Job>();
+                // exits once the runtime and every pending callback are gone
+                std::thread::spawn(move || {
+                    for job in rx {
+                        job();
+                    }
+                });
+                tx
+            })
+            .clone()
+    }
+}
+
+enum Target {
+    Thread(mpsc::Sender<Job>),
+    Executor(Arc<dyn CompletionExecutor>),
+}
+
+impl Target {
+    fn post(&self, job: Job) {
+        match self {
+            Target::Thread(tx) => {
+                let _ = tx.send(job);
+            }
+            Target::Executor(executor) => executor.post(job),
+        }
+    }
+}
+
+/// A callback waiting for the outcome of its task. If the last reference is
+/// dropped before it was called, the task never ran.
+struct Callback<R: Send + 'static> {
+    on_done: Mutex<Option<OnDone<R>>>,
+    target: Target,
+    label: &'static str,
+    model: ComModel,
+    /// Tells why the task was lost if the callback is dropped uncalled.
+    apartment: Weak<Apartment>,
+}
+
+impl<R: Send + 'static> Callback<R> {
+    fn complete(&self, res: Result<R, CallError>) {
+        let Some(on_done) = self.on_done.lock().unwrap().take() else {
+            return;
+        };
+        let label = self.label;
+        self.target.post(Box::new(move || {
+            if let Err(payload) = catch_unwind(AssertUnwindSafe(|| on_done(res))) {
+                detached::report(DetachedError::Panicked {
+                    label,
+                    message: detached::panic_message(&*payload),
+                });
+            }
+        }));
+    }
+}
+
+impl<R: Send + 'static> Drop for Callback<R> {
+    fn drop(&mut self) {
+        let error = match self.apartment.upgrade() {
+            Some(apartment) => apartment.lost(),
+            None => CallError::ShutDown(self.model),
+        };
+        self.complete(Err(error));
+    }
+}
+
+/// Runs the task, then hands its result to the callback. A panic unwinds
+/// through the worker as usual and reaches the callback as
+/// [`CallError::Panicked`].
+struct CallbackTask<F, R: Send + 'static> {
+    f: Option<F>,
+    callback: Arc<Callback<R>>,
+}
+
+impl<F, R> Task for CallbackTask<F, R>
+where
+    F: FnOnce() -> R + Send,
+    R: Send + 'static,
+{
+    fn label(&self) -> &'static str {
+        self.callback.label
+    }
+
+    fn run(mut self: Box<Self>) -> Box<dyn Any + Send> {
+        let f = self.f.take().expect("task already taken");
+        let r = f();
+        self.callback.complete(Ok(r));
+        Box::new(())
+    }
+}
+
+impl<F, R: Send + 'static> Drop for CallbackTask<F, R> {
+    fn drop(&mut self) {
+        // taken but not completed: `f` is unwinding
+        if self.f.is_none() && std::thread::panicking() {
+            self.callback
+                .complete(Err(CallError::Panicked(self.callback.model)));
+        }
+    }
+}
+
+/// Run `f` on the apartment for `model` and call `on_done` with its result
+/// on the runtime's completion thread, without blocking the caller.
+///
+/// `on_done` is called exactly once: with the result, with
+/// [`CallError::Panicked`] if `f` panicked (the panic itself goes to the
+/// [error sink](crate::set_error_sink)), or with the error that kept `f`
+/// from running. Callbacks run one at a time and should return quickly.
+pub fn call_with_callback<F, R, D>(model: ComModel, f: F, on_done: D)
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+    D: FnOnce(Result<R, CallError>) + Send + 'static,
+{
+    ComRuntime::global().call_with_callback(model, f, on_done)
+}
+
+/// Like [`call_with_callback`], posting `on_done` to `executor` instead of
+/// the completion thread.
+pub fn call_with_executor<F, R, E, D>(model: ComModel, f: F, executor: E, on_done: D)
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+    E: CompletionExecutor + 'static,
+    D: FnOnce(Result<R, CallError>) + Send + 'static,
+{
+    ComRuntime::global().call_with_executor(model, f, executor, on_done)
+}
+
+/// Queue `f` on the apartment for `model` and return a handle that can be
+/// polled for the result without blocking.
+pub fn start_call<F, R>(model: ComModel, f: F) -> PendingCall<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    ComRuntime::global().start_call(model, f)
+}
+
+impl ComRuntime {
+    /// See [`call_with_callback`].
+    pub fn call_with_callback<F, R, D>(&self, model: ComModel, f: F, on_done: D)
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+        D: FnOnce(Result<R, CallError>) + Send + 'static,
+    {
+        let target = Target::Thread(self.shared.completions.sender());
+        dispatch_callback(self, model, std::any::type_name::<F>(), f, target, on_done)
+    }
+
+    /// See [`call_with_executor`].
+    pub fn call_with_executor<F, R, E, D>(&self, model: ComModel, f: F, executor: E, on_done: D)
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+        E: CompletionExecutor + 'static,
+        D: FnOnce(Result<R, CallError>) + Send + 'static,
+    {
+        let target = Target::Executor(Arc::new(executor));
+        dispatch_callback(self, model, std::any::type_name::<F>(), f, target, on_done)
+    }
+
+    /// See [`start_call`].
+    pub fn start_call<F, R>(&self, model: ComModel, f: F) -> PendingCall<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Any + Send + 'static,
+    {
+        let (resp_tx, resp_rx) = mpsc::channel::<Reply>();
+        let task = Box::new(TaskImpl {
+            f: Some(f),
+            label: std::any::type_name::<F>(),
+        });
+        let state = match self.submit(model, Message::Sync(task, resp_tx)) {
+            Ok(apartment) => Pending::Queued(resp_rx, apartment),
+            Err(error) => Pending::Done(Err(error)),
+        };
+        PendingCall {
+            state,
+            _result: PhantomData,
+        }
+    }
+}
+
+fn dispatch_callback<F, R, D>(
+    rt: &ComRuntime,
+    model: ComModel,
+    label: &'static str,
+    f: F,
+    target: Target,
+    on_done: D,
+) where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+    D: FnOnce(Result<R, CallError>) + Send + 'static,
+{
+    // if there is no apartment, submitting fails and says why
+    let apartment = rt
+        .apartment(model)
+        .map_or_else(|_| Weak::new(), |apartment| Arc::downgrade(&apartment));
+    let callback = Arc::new(Callback {
+        on_done: Mutex::new(Some(Box::new(on_done))),
+        target,
+        label,
+        model,
+        apartment,
+    });
+    let task = Box::new(CallbackTask {
+        f: Some(f),
+        callback: callback.clone(),
+    });
+    if let Err(error) = rt.submit(model, Message::Detached(task)) {
+        callback.complete(Err(error));
+    }
+}
+
+enum Pending {
+    Queued(mpsc::Receiver<Reply>, Arc<Apartment>),
+    Done(Result<Reply, CallError>),
+    Taken,
+}
+
+/// A call started with [`start_call`], for callers that can neither block
+/// nor poll a future, such as a GUI loop checking on each tick.
+#[must_use = "the result is lost if the handle is dropped"]
+pub struct PendingCall<R> {
+    state: Pending,
+    _result: PhantomData<fn() -> R>,
+}
+
+impl<R: Any> PendingCall<R> {
+    /// Whether the result is ready to be taken.
+    pub fn is_finished(&mut self) -> bool {
+        self.poll();
+        matches!(self.state, Pending::Done(_))
+    }
+
+    /// Take the result if the call has finished. Returns `None` while it is
+    /// still queued or running, and after the result has been taken.
+    ///
+    /// A panic inside the task is re-raised here, like
+    /// [`call_sync`](crate::call_sync).
+    pub fn try_take(&mut self) -> Option<Result<R, CallError>> {
+        self.poll();
+        match std::mem::replace(&mut self.state, Pending::Taken) {
+            Pending::Done(res) => Some(res.map(unpack)),
+            state => {
+                self.state = state;
+                None
+            }
+        }
+    }
+
+    /// Block until the call finishes and return its result.
+    pub fn wait(mut self) -> Result<R, CallError> {
+        match std::mem::replace(&mut self.state, Pending::Taken) {
+            Pending::Queued(rx, apartment) => rx.recv().map(unpack).map_err(|_| apartment.lost()),
+            Pending::Done(res) => res.map(unpack),
+            Pending::Taken => panic!("result already taken"),
+        }
+    }
+
+    fn poll(&mut self) {
+        if let Pending::Queued(rx, apartment) = &self.state {
+            match rx.try_recv() {
+                Ok(reply) => self.state = Pending::Done(Ok(reply)),
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.state = Pending::Done(Err(apartment.lost()))
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,458 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::panic::{AssertUnwindSafe, catch_unwind, resume_unwind};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::FutureExt;
+use windows::core::HRESULT;
+
+use crate::stats::{BreakerStats, CircuitStats};
+use crate::{ComModel, ComRuntime, Message, Task};
+
+/// When a circuit breaker opens and how long it stays open.
+///
+/// The breaker opens after `failures` failures in a row, all within
+/// `window`. While open, calls fail right away. Once `cooldown` has passed,
+/// one call at a time is let through as a trial while the others keep
+/// failing: a trial that succeeds closes the breaker, one that fails opens
+/// it for another `cooldown`. A trial still unfinished after a further
+/// `cooldown` no longer holds back the next one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CircuitPolicy {
+    failures: u32,
+    window: Duration,
+    cooldown: Duration,
+}
+
+impl CircuitPolicy {
+    pub const fn new(failures: u32, window: Duration, cooldown: Duration) -> Self {
+        assert!(
+            failures > 0,
+            "a circuit breaker needs a non-zero failure count"
+        );
+        CircuitPolicy {
+            failures,
+            window,
+            cooldown,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Calls run normally.
+    Closed,
+    /// Calls fail without running until the cooldown has passed.
+    Open,
+    /// The cooldown has passed; one call at a time runs as a trial.
+    HalfOpen,
+}
+
+/// Returned by a function whose [`CircuitBreaker`] is open, instead of
+/// running it.
+///
+/// Converts into a `windows::core::Error` with the code
+/// [`CircuitOpen::CODE`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CircuitOpen {
+    /// Label of the breaker.
+    pub label: &'static str,
+}
+
+impl CircuitOpen {
+    /// `E_ABORT`, the code of the `windows::core::Error` this converts into.
+    pub const CODE: HRESULT = HRESULT(0x8000_4004_u32 as i32);
+}
+
+impl fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "circuit breaker of {} is open", self.label)
+    }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+impl From<CircuitOpen> for windows::core::Error {
+    fn from(open: CircuitOpen) -> Self {
+        windows::core::Error::new(CircuitOpen::CODE, open.to_string())
+    }
+}
+
+/// Breaker state shared by apartments and [`CircuitBreaker`].
+pub(crate) struct Circuit {
+    inner: Mutex<CircuitInner>,
+    opened: AtomicU64,
+    rejected: AtomicU64,
+}
+
+struct CircuitInner {
+    /// `None` disables the breaker.
+    policy: Option<CircuitPolicy>,
+    /// Failures since the last success, oldest first.
+    failures: VecDeque<Instant>,
+    open_until: Option<Instant>,
+    /// When the half-open breaker admitted the trial in flight.
+    trial: Option<Instant>,
+}
+
+impl Circuit {
+    pub(crate) const fn new(policy: Option<CircuitPolicy>) -> Self {
+        Circuit {
+            inner: Mutex::new(CircuitInner {
+                policy,
+                failures: VecDeque::new(),
+                open_until: None,
+                trial: None,
+            }),
+            opened: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// Switch to `policy`, closing the breaker if it changed.
+    pub(crate) fn configure(&self, policy: Option<CircuitPolicy>) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.policy != policy {
+            *inner = CircuitInner {
+                policy,
+                failures: VecDeque::new(),
+                open_until: None,
+                trial: None,
+            };
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.inner.lock().unwrap().policy.is_some()
+    }
+
+    /// Make `msg` record its outcome, if a policy is set. Recorded before
+    /// the reply is sent, so a caller that saw a task fail sees its effect
+    /// on the breaker.
+    pub(crate) fn guard(self: &Arc<Self>, msg: Message) -> Message {
+        if !self.is_enabled() {
+            return msg;
+        }
+        msg.wrap(|task| {
+            Box::new(CircuitTask {
+                task,
+                circuit: self.clone(),
+            })
+        })
+    }
+
+    /// Whether a call may run now. Every admitted call must be recorded,
+    /// or a half-open breaker waits out the cooldown for another trial.
+    pub(crate) fn admit(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let (Some(open_until), Some(policy)) = (inner.open_until, inner.policy) else {
+            return true;
+        };
+        let now = Instant::now();
+        let trial_running = inner
+            .trial
+            .is_some_and(|started| now.duration_since(started) < policy.cooldown);
+        if now < open_until || trial_running {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        inner.trial = Some(now);
+        true
+    }
+
+    /// Record how an admitted call ended.
+    pub(crate) fn record(&self, succeeded: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(policy) = inner.policy else {
+            return;
+        };
+        let now = Instant::now();
+        if let Some(open_until) = inner.open_until {
+            // calls admitted before the breaker opened do not count
+            if now < open_until {
+                return;
+            }
+            inner.trial = None;
+            if succeeded {
+                inner.open_until = None;
+            } else {
+                self.open(&mut inner, policy, now);
+            }
+            return;
+        }
+        if succeeded {
+            inner.failures.clear();
+            return;
+        }
+        inner.failures.push_back(now);
+        while inner
+            .failures
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > policy.window)
+        {
+            inner.failures.pop_front();
+        }
+        if inner.failures.len() >= policy.failures as usize {
+            self.open(&mut inner, policy, now);
+        }
+    }
+
+    fn open(&self, inner: &mut CircuitInner, policy: CircuitPolicy, now: Instant) {
+        inner.failures.clear();
+        inner.open_until = Some(now + policy.cooldown);
+        inner.trial = None;
+        self.opened.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn state(&self) -> CircuitState {
+        match self.inner.lock().unwrap().open_until {
+            None => CircuitState::Closed,
+            Some(open_until) if Instant::now() < open_until => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    pub(crate) fn stats(&self) -> CircuitStats {
+        CircuitStats {
+            state: self.state(),
+            recent_failures: self.inner.lock().unwrap().failures.len(),
+            opened: self.opened.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+struct CircuitTask {
+    task: Box<dyn Task>,
+    circuit: Arc<Circuit>,
+}
+
+impl Task for CircuitTask {
+    fn label(&self) -> &'static str {
+        self.task.label()
+    }
+
+    fn run(self: Box<Self>) -> Box<dyn std::any::Any + Send> {
+        let this = *self;
+        let res = catch_unwind(AssertUnwindSafe(|| this.task.run()));
+        this.circuit.record(res.is_ok());
+        res.unwrap_or_else(|payload| resume_unwind(payload))
+    }
+
+    fn watched(&self) -> bool {
+        self.task.watched()
+    }
+}
+
+/// A circuit breaker guarding the calls of one function.
+///
+/// Backs `#[com_thread(circuit_breaker(...))]`, which declares one per
+/// function. A call fails when it returns an error or panics.
+///
+/// ```ignore
+/// static WMI: CircuitBreaker = CircuitBreaker::new(
+///     "wmi",
+///     CircuitPolicy::new(5, Duration::from_secs(30), Duration::from_secs(10)),
+/// );
+///
+/// let disks = call_with_breaker(ComModel::MTA, &WMI, || query_disks())?;
+/// ```
+pub struct CircuitBreaker {
+    label: &'static str,
+    circuit: Circuit,
+}
+
+impl CircuitBreaker {
+    /// A closed breaker reported as `label` in runtime stats.
+    pub const fn new(label: &'static str, policy: CircuitPolicy) -> Self {
+        CircuitBreaker {
+            label,
+            circuit: Circuit::new(Some(policy)),
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.circuit.state()
+    }
+
+    pub fn stats(&self) -> CircuitStats {
+        self.circuit.stats()
+    }
+
+    /// Close the breaker and forget recent failures.
+    pub fn reset(&self) {
+        let mut inner = self.circuit.inner.lock().unwrap();
+        inner.failures.clear();
+        inner.open_until = None;
+        inner.trial = None;
+    }
+
+    fn open_error(&self) -> windows::core::Error {
+        CircuitOpen { label: self.label }.into()
+    }
+}
+
+/// Circuit breakers used through a runtime, listed in its stats.
+#[derive(Default)]
+pub(crate) struct Breakers(Mutex<Vec<&'static CircuitBreaker>>);
+
+impl Breakers {
+    fn register(&self, breaker: &'static CircuitBreaker) {
+        let mut breakers = self.0.lock().unwrap();
+        if !breakers.iter().any(|b| std::ptr::eq(*b, breaker)) {
+            breakers.push(breaker);
+        }
+    }
+
+    pub(crate) fn stats(&self) -> Vec<BreakerStats> {
+        let breakers = self.0.lock().unwrap();
+        breakers
+            .iter()
+            .map(|b| BreakerStats {
+                label: b.label,
+                circuit: b.stats(),
+            })
+            .collect()
+    }
+}
+
+/// Run `f` on the apartment for `model` unless `breaker` is open, in which
+/// case a [`CircuitOpen`] error is returned without running it.
+///
+/// An error returned by `f` or a panic in `f` counts as a failure; the
+/// panic is re-raised.
+pub fn call_with_breaker<F, R>(
+    model: ComModel,
+    breaker: &'static CircuitBreaker,
+    f: F,
+) -> windows::core::Result<R>
+where
+    F: FnOnce() -> windows::core::Result<R> + Send + 'static,
+    R: Send + 'static,
+{
+    ComRuntime::global().call_with_breaker(model, breaker, f)
+}
+
+/// Like [`call_with_breaker`], with a label identifying the task in stats
+/// and watchdog reports.
+pub fn call_with_breaker_labeled<F, R>(
+    model: ComModel,
+    breaker: &'static CircuitBreaker,
+    label: &'static str,
+    f: F,
+) -> windows::core::Result<R>
+where
+    F: FnOnce() -> windows::core::Result<R> + Send + 'static,
+    R: Send + 'static,
+{
+    ComRuntime::global().call_with_breaker_labeled(model, breaker, label, f)
+}
+
+/// Async version of [`call_with_breaker`].
+pub fn call_with_breaker_async<F, R>(
+    model: ComModel,
+    breaker: &'static CircuitBreaker,
+    f: F,
+) -> impl Future<Output = windows::core::Result<R>> + use<F, R>
+where
+    F: FnOnce() -> windows::core::Result<R> + Send + 'static,
+    R: Send + 'static,
+{
+    ComRuntime::global().call_with_breaker_async(model, breaker, f)
+}
+
+/// Async version of [`call_with_breaker_labeled`].
+pub fn call_with_breaker_async_labeled<F, R>(
+    model: ComModel,
+    breaker: &'static CircuitBreaker,
+    label: &'static str,
+    f: F,
+) -> impl Future<Output = windows::core::Result<R>> + use<F, R>
+where
+    F: FnOnce() -> windows::core::Result<R> + Send + 'static,
+    R: Send + 'static,
+{
+    ComRuntime::global().call_with_breaker_async_labeled(model, breaker, label, f)
+}
+
+impl ComRuntime {
+    /// See [`call_with_breaker`].
+    pub fn call_with_breaker<F, R>(
+        &self,
+        model: ComModel,
+        breaker: &'static CircuitBreaker,
+        f: F,
+    ) -> windows::core::Result<R>
+    where
+        F: FnOnce() -> windows::core::Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        self.call_with_breaker_labeled(model, breaker, std::any::type_name::<F>(), f)
+    }
+
+    /// See [`call_with_breaker_labeled`].
+    pub fn call_with_breaker_labeled<F, R>(
+        &self,
+        model: ComModel,
+        breaker: &'static CircuitBreaker,
+        label: &'static str,
+        f: F,
+    ) -> windows::core::Result<R>
+    where
+        F: FnOnce() -> windows::core::Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        self.shared.breakers.register(breaker);
+        if !breaker.circuit.admit() {
+            return Err(breaker.open_error());
+        }
+        let res = catch_unwind(AssertUnwindSafe(|| self.call_sync_labeled(model, label, f)));
+        breaker.circuit.record(matches!(res, Ok(Ok(_))));
+        res.unwrap_or_else(|payload| resume_unwind(payload))
+    }
+
+    /// See [`call_with_breaker_async`].
+    pub fn call_with_breaker_async<F, R>(
+        &self,
+        model: ComModel,
+        breaker: &'static CircuitBreaker,
+        f: F,
+    ) -> impl Future<Output = windows::core::Result<R>> + use<F, R>
+    where
+        F: FnOnce() -> windows::core::Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        self.call_with_breaker_async_labeled(model, breaker, std::any::type_name::<F>(), f)
+    }
+
+    /// See [`call_with_breaker_async_labeled`].
+    pub fn call_with_breaker_async_labeled<F, R>(
+        &self,
+        model: ComModel,
+        breaker: &'static CircuitBreaker,
+        label: &'static str,
+        f: F,
+    ) -> impl Future<Output = windows::core::Result<R>> + use<F, R>
+    where
+        F: FnOnce() -> windows::core::Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        self.shared.breakers.register(breaker);
+        // admitted and queued eagerly, like other async calls
+        let call = breaker
+            .circuit
+            .admit()
+            .then(|| self.call_async_labeled(model, label, f));
+        async move {
+            let Some(call) = call else {
+                return Err(breaker.open_error());
+            };
+            let res = AssertUnwindSafe(call).catch_unwind().await;
+            breaker.circuit.record(matches!(res, Ok(Ok(_))));
+            res.unwrap_or_else(|payload| resume_unwind(payload))
+        }
+    }
+}
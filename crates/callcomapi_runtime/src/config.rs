@@ -0,0 +1,205 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::{ComModel, ComRuntime};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RestartLimit {
+    Never,
+    Always,
+    Limited { max_restarts: u32, within: Duration },
+}
+
+/// How an apartment recovers when its worker dies.
+///
+/// A worker dies when COM initialization fails or when any task it runs
+/// panics, including detached tasks and timers whose panics only reach the
+/// [error sink](crate::set_error_sink); a call's panic is still propagated
+/// to its caller. Restarts are delayed by an exponential backoff that resets
+/// once a task completes normally.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RestartPolicy {
+    limit: RestartLimit,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl RestartPolicy {
+    /// Fail the apartment as soon as its worker dies, so the first panic of
+    /// any of its tasks, detached and scheduled ones included, fails every
+    /// later call with [`CallError::ApartmentFailed`](crate::CallError::ApartmentFailed).
+    pub fn never() -> Self {
+        Self::with_limit(RestartLimit::Never)
+    }
+
+    /// Always restart the worker.
+    pub fn always() -> Self {
+        Self::with_limit(RestartLimit::Always)
+    }
+
+    /// Allow at most `max_restarts` restarts within any `within` window.
+    pub fn limited(max_restarts: u32, within: Duration) -> Self {
+        Self::with_limit(RestartLimit::Limited {
+            max_restarts,
+            within,
+        })
+    }
+
+    /// Delay the first restart by `initial`, doubling on each consecutive
+    /// restart up to `max`.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    fn with_limit(limit: RestartLimit) -> Self {
+        RestartPolicy {
+            limit,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+
+    /// Record a restart at `now` if the policy still allows one.
+    pub(crate) fn admit(&self, history: &mut VecDeque<Instant>, now: Instant) -> bool {
+        match self.limit {
+            RestartLimit::Never => false,
+            RestartLimit::Always => true,
+            RestartLimit::Limited {
+                max_restarts,
+                within,
+            } => {
+                while history
+                    .front()
+                    .is_some_and(|t| now.duration_since(*t) >= within)
+                {
+                    history.pop_front();
+                }
+                if history.len() >= max_restarts as usize {
+                    return false;
+                }
+                history.push_back(now);
+                true
+            }
+        }
+    }
+
+    /// Delay before the `consecutive`-th restart in a row (1-based).
+    pub(crate) fn backoff_for(&self, consecutive: u32) -> Duration {
+        let factor = 2u32.saturating_pow(consecutive.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self::always()
+    }
+}
+
+/// When a healthy worker is retired in favour of a fresh one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct RecyclePolicy {
+    max_tasks: Option<u64>,
+    max_age: Option<Duration>,
+}
+
+impl RecyclePolicy {
+    pub(crate) fn is_due(&self, tasks: u64, age: Duration) -> bool {
+        self.max_tasks.is_some_and(|max| tasks >= max) || self.max_age.is_some_and(|max| age >= max)
+    }
+
+    /// Time left before the age limit, if there is one.
+    pub(crate) fn remaining_age(&self, age: Duration) -> Option<Duration> {
+        self.max_age.map(|max| max.saturating_sub(age))
+    }
+}
+
+/// Per-apartment settings, see [`configure_apartment`].
+#[derive(Clone, Debug, Default)]
+pub struct ApartmentConfig {
+    pub(crate) restart: RestartPolicy,
+    pub(crate) recycle: RecyclePolicy,
+    #[cfg(feature = "tokio")]
+    pub(crate) tokio: crate::TokioMode,
+    pub(crate) ephemeral_spares: usize,
+    pub(crate) keyed_workers: usize,
+    pub(crate) circuit: Option<crate::CircuitPolicy>,
+}
+
+impl ApartmentConfig {
+    /// How the apartment's workers are restarted after a task panics or COM
+    /// cannot be initialized. Defaults to [`RestartPolicy::always`].
+    pub fn restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.restart = policy;
+        self
+    }
+
+    /// How async task bodies reach a tokio runtime. Applies to workers
+    /// started after the change.
+    #[cfg(feature = "tokio")]
+    pub fn tokio_mode(mut self, mode: crate::TokioMode) -> Self {
+        self.tokio = mode;
+        self
+    }
+
+    /// Keep `spares` threads with COM already initialized, each ready to run
+    /// one ephemeral call (see [`call_sync_ephemeral`](crate::call_sync_ephemeral))
+    /// without paying for thread startup. Defaults to none.
+    pub fn ephemeral_spares(mut self, spares: usize) -> Self {
+        self.ephemeral_spares = spares;
+        self
+    }
+
+    /// Serve [`call_keyed`](crate::call_keyed) with a pool of `workers`
+    /// threads in this apartment, each key always going to the same one.
+    /// Defaults to none, where keyed calls share the apartment's worker.
+    ///
+    /// Changing the count while keyed calls are queued may reorder them.
+    pub fn keyed_workers(mut self, workers: usize) -> Self {
+        self.keyed_workers = workers;
+        self
+    }
+
+    /// Fail calls to this apartment with [`CallError::CircuitOpen`] while its
+    /// tasks keep failing, see [`CircuitPolicy`](crate::CircuitPolicy). A
+    /// task fails only when it panics or the watchdog reports it hung; one
+    /// that returns an error, like an `Err(HRESULT)`, counts as a success,
+    /// since the apartment does not look at return values. Scheduled tasks
+    /// are not counted. To trip on returned errors, guard the calls with a
+    /// [`CircuitBreaker`](crate::CircuitBreaker) instead. Defaults to no
+    /// breaker.
+    ///
+    /// [`CallError::CircuitOpen`]: crate::CallError::CircuitOpen
+    pub fn circuit_breaker(mut self, policy: crate::CircuitPolicy) -> Self {
+        self.circuit = Some(policy);
+        self
+    }
+
+    /// Retire each worker after it has completed `tasks` tasks.
+    ///
+    /// Retirement is graceful: the worker finishes its current task, a freshly
+    /// initialized worker takes over the queue, and the old thread then
+    /// uninitializes COM and exits. Useful for COM servers that leak per call.
+    pub fn recycle_after_tasks(mut self, tasks: u64) -> Self {
+        assert!(tasks > 0, "recycle task limit must be non-zero");
+        self.recycle.max_tasks = Some(tasks);
+        self
+    }
+
+    /// Retire each worker once it has been running for `age`, see
+    /// [`recycle_after_tasks`](Self::recycle_after_tasks).
+    pub fn recycle_after_age(mut self, age: Duration) -> Self {
+        assert!(!age.is_zero(), "recycle age limit must be non-zero");
+        self.recycle.max_age = Some(age);
+        self
+    }
+}
+
+/// Apply `config` to the apartment for `model`, starting it if needed.
+pub fn configure_apartment(model: ComModel, config: ApartmentConfig) {
+    ComRuntime::global().configure_apartment(model, config);
+}
@@ -0,0 +1,157 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{ComRuntime, Message, Task};
+
+/// Hooks carrying caller-side context into tasks, in registration order.
+#[derive(Default)]
+pub(crate) struct ContextHooks {
+    hooks: Mutex<Vec<Arc<dyn Hook>>>,
+}
+
+trait Hook: Send + Sync {
+    fn capture(&self) -> Box<dyn Captured>;
+}
+
+/// A value taken on one thread, waiting to be installed on another.
+trait Captured: Send {
+    /// Install the value, returning the one it replaced.
+    fn restore(self: Box<Self>) -> Box<dyn Captured>;
+}
+
+struct HookImpl<C, S> {
+    capture: C,
+    swap: Arc<S>,
+}
+
+struct Value<T, S> {
+    value: T,
+    swap: Arc<S>,
+}
+
+impl<C, S, T> Hook for HookImpl<C, S>
+where
+    C: Fn() -> T + Send + Sync,
+    S: Fn(T) -> T + Send + Sync + 'static,
+    T: Send + 'static,
+{
+    fn capture(&self) -> Box<dyn Captured> {
+        Box::new(Value {
+            value: (self.capture)(),
+            swap: self.swap.clone(),
+        })
+    }
+}
+
+impl<S, T> Captured for Value<T, S>
+where
+    S: Fn(T) -> T + Send + Sync + 'static,
+    T: Send + 'static,
+{
+    fn restore(self: Box<Self>) -> Box<dyn Captured> {
+        let previous = (self.swap)(self.value);
+        Box::new(Value {
+            value: previous,
+            swap: self.swap,
+        })
+    }
+}
+
+/// Register a hook carrying context from the submitting thread into every
+/// task of the global runtime. See [`ComRuntime::add_context_hook`].
+pub fn add_context_hook<C, S, T>(capture: C, restore: S)
+where
+    C: Fn() -> T + Send + Sync + 'static,
+    S: Fn(T) -> T + Send + Sync + 'static,
+    T: Send + 'static,
+{
+    ComRuntime::global().add_context_hook(capture, restore)
+}
+
+/// Remove every context hook of the global runtime.
+pub fn clear_context_hooks() {
+    ComRuntime::global().clear_context_hooks()
+}
+
+impl ComRuntime {
+    /// Register a hook carrying context, such as a request ID kept in a
+    /// thread-local, from the submitting thread into every task.
+    ///
+    /// `capture` runs on the submitting thread when a task is queued.
+    /// `restore` runs on the worker right before the task with the captured
+    /// value and returns the value it replaced; once the task finishes, even
+    /// by panicking, `restore` runs again with that previous value, so
+    /// nothing leaks into the worker's next task.
+    ///
+    /// ```ignore
+    /// thread_local!(static REQUEST_ID: Cell<Option<u64>> = const { Cell::new(None) });
+    ///
+    /// add_context_hook(|| REQUEST_ID.get(), |id| REQUEST_ID.replace(id));
+    /// ```
+    pub fn add_context_hook<C, S, T>(&self, capture: C, restore: S)
+    where
+        C: Fn() -> T + Send + Sync + 'static,
+        S: Fn(T) -> T + Send + Sync + 'static,
+        T: Send + 'static,
+    {
+        let hook = HookImpl {
+            capture,
+            swap: Arc::new(restore),
+        };
+        self.shared
+            .contexts
+            .hooks
+            .lock()
+            .unwrap()
+            .push(Arc::new(hook));
+    }
+
+    /// Remove every context hook of this runtime. Tasks already queued keep
+    /// the context they captured.
+    pub fn clear_context_hooks(&self) {
+        self.shared.contexts.hooks.lock().unwrap().clear();
+    }
+}
+
+impl ContextHooks {
+    /// Capture the current context into `msg`'s task.
+    pub(crate) fn capture(&self, msg: Message) -> Message {
+        let hooks = self.hooks.lock().unwrap().clone();
+        if hooks.is_empty() {
+            return msg;
+        }
+        let captured = hooks.iter().map(|hook| hook.capture()).collect();
+        msg.wrap(|task| Box::new(ContextTask { task, captured }))
+    }
+}
+
+struct ContextTask {
+    task: Box<dyn Task>,
+    captured: Vec<Box<dyn Captured>>,
+}
+
+impl Task for ContextTask {
+    fn label(&self) -> &'static str {
+        self.task.label()
+    }
+
+    fn run(self: Box<Self>) -> Box<dyn std::any::Any + Send> {
+        let this = *self;
+        let _restore = Restore(this.captured.into_iter().map(Captured::restore).collect());
+        this.task.run()
+    }
+
+    fn watched(&self) -> bool {
+        self.task.watched()
+    }
+}
+
+/// Puts the replaced values back, in reverse order, when dropped.
+struct Restore(Vec<Box<dyn Captured>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        while let Some(previous) = self.0.pop() {
+            previous.restore();
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::cell::{Cell, RefCell};
+
+use crate::ComModel;
+
+thread_local! {
+    /// Models of the COM initializations made through this crate on this
+    /// thread, innermost last.
+    static MODELS: RefCell<Vec<ComModel>> = const { RefCell::new(Vec::new()) };
+    static WORKER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The COM apartment the current thread was put in by this crate, through
+/// [`init_com`](crate::init_com), `#[with_com]` or an apartment worker.
+///
+/// Returns `None` when none of those initialized COM on this thread, even
+/// if other code called `CoInitializeEx` directly. Inline tasks (see
+/// [`ComRuntime::set_inline`](crate::ComRuntime::set_inline)) report the
+/// calling thread's apartment.
+pub fn current_apartment() -> Option<ComModel> {
+    MODELS.with_borrow(|models| models.last().copied())
+}
+
+/// Whether the current thread is an apartment worker of some [`ComRuntime`](crate::ComRuntime).
+pub fn is_runtime_worker() -> bool {
+    WORKER.get()
+}
+
+/// Panic unless the current thread is in the `STA` or `MTA` apartment,
+/// as reported by [`current_apartment`].
+///
+/// ```ignore
+/// fn read_clipboard() -> String {
+///     assert_apartment!(STA);
+///     // ...
+/// }
+/// ```
+#[macro_export]
+macro_rules! assert_apartment {
+    ($model:ident) => {
+        $crate::__assert_apartment($crate::ComModel::$model)
+    };
+}
+
+#[doc(hidden)]
+#[track_caller]
+pub fn __assert_apartment(expected: ComModel) {
+    match current_apartment() {
+        Some(model) if model == expected => {}
+        Some(model) => panic!(
+            "expected the current thread to be in the {expected:?} apartment, but it is in the {model:?} apartment"
+        ),
+        None => panic!(
+            "expected the current thread to be in the {expected:?} apartment, but COM was not initialized on it by callcomapi"
+        ),
+    }
+}
+
+pub(crate) fn push(model: ComModel) {
+    MODELS.with_borrow_mut(|models| models.push(model));
+}
+
+pub(crate) fn pop() {
+    MODELS.with_borrow_mut(|models| models.pop());
+}
+
+pub(crate) fn mark_worker() {
+    WORKER.set(true);
+}
+
+/// Undo [`mark_worker`] on a thread that stops hosting an apartment.
+pub(crate) fn unmark_worker() {
+    WORKER.set(false);
+}
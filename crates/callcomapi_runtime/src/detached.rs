@@ -0,0 +1,111 @@
+use std::any::Any;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::{CallError, ComModel, ComRuntime, Message, TaskImpl};
+
+/// Why a detached task did not complete.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum DetachedError {
+    /// The task panicked. `message` holds the panic message when it was a string.
+    Panicked {
+        label: &'static str,
+        message: String,
+    },
+    /// The task never ran.
+    Call {
+        label: &'static str,
+        error: CallError,
+    },
+}
+
+impl fmt::Display for DetachedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DetachedError::Panicked { label, message } => {
+                write!(f, "detached task `{label}` panicked: {message}")
+            }
+            DetachedError::Call { label, error } => {
+                write!(f, "detached task `{label}` did not run: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DetachedError {}
+
+type ErrorSink = Arc<dyn Fn(&DetachedError) + Send + Sync>;
+
+static ERROR_SINK: Mutex<Option<ErrorSink>> = Mutex::new(None);
+
+/// Install the handler receiving failures of detached tasks, replacing any
+/// previous one. Without a handler failures are written to stderr.
+///
+/// The handler runs on the apartment worker (or the submitting thread if the
+/// task could not be queued) and must not block.
+pub fn set_error_sink<F>(f: F)
+where
+    F: Fn(&DetachedError) + Send + Sync + 'static,
+{
+    *ERROR_SINK.lock().unwrap() = Some(Arc::new(f));
+}
+
+pub(crate) fn report(error: DetachedError) {
+    let sink = ERROR_SINK.lock().unwrap().clone();
+    match sink {
+        Some(sink) => sink(&error),
+        None => eprintln!("callcomapi: {error}"),
+    }
+}
+
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "<non-string panic payload>".to_string()
+    }
+}
+
+/// Queue `f` on the apartment for `model` without waiting for it.
+///
+/// No reply channel is allocated. If `f` panics, or the apartment cannot
+/// run it, the failure goes to the sink installed with [`set_error_sink`].
+pub fn spawn_detached<F>(model: ComModel, f: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    ComRuntime::global().spawn_detached(model, f)
+}
+
+/// Like [`spawn_detached`], with a label identifying the task in stats,
+/// watchdog reports and the error sink.
+pub fn spawn_detached_labeled<F>(model: ComModel, label: &'static str, f: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    ComRuntime::global().spawn_detached_labeled(model, label, f)
+}
+
+impl ComRuntime {
+    /// See [`spawn_detached`].
+    pub fn spawn_detached<F>(&self, model: ComModel, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.spawn_detached_labeled(model, std::any::type_name::<F>(), f)
+    }
+
+    /// See [`spawn_detached_labeled`].
+    pub fn spawn_detached_labeled<F>(&self, model: ComModel, label: &'static str, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let task = Box::new(TaskImpl { f: Some(f), label });
+        if let Err(error) = self.submit(model, Message::Detached(task)) {
+            report(DetachedError::Call { label, error });
+        }
+    }
+}
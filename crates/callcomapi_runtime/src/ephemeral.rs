@@ -0,0 +1,177 @@
+use std::any::Any;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, mpsc};
+
+use crate::apartment::Apartment;
+use crate::{CallError, ComGuard, ComModel, ComRuntime, Message, Reply, TaskImpl, unpack};
+
+/// Threads with COM initialized, each waiting for a single ephemeral task.
+#[derive(Default)]
+pub(crate) struct Spares {
+    inner: Mutex<SparesInner>,
+}
+
+#[derive(Default)]
+struct SparesInner {
+    senders: Vec<mpsc::Sender<Message>>,
+    closed: bool,
+}
+
+impl Spares {
+    fn take(&self) -> Option<mpsc::Sender<Message>> {
+        self.inner.lock().unwrap().senders.pop()
+    }
+
+    /// Let the idle spares exit and stop making new ones.
+    pub(crate) fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.closed = true;
+        inner.senders.clear();
+    }
+}
+
+/// Run `f` on a fresh thread in the `model` apartment, which uninitializes
+/// COM and exits once `f` returns.
+///
+/// For COM servers that leave their apartment in a bad state. Results and
+/// panics are delivered like [`call_sync`](crate::call_sync); a panic only
+/// ends the task's own thread. See [`ApartmentConfig::ephemeral_spares`](crate::ApartmentConfig::ephemeral_spares)
+/// to hide the thread startup.
+pub fn call_sync_ephemeral<F, R>(model: ComModel, f: F) -> R
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    ComRuntime::global().call_sync_ephemeral(model, f)
+}
+
+/// Like [`call_sync_ephemeral`], with a label identifying the task.
+pub fn call_sync_ephemeral_labeled<F, R>(model: ComModel, label: &'static str, f: F) -> R
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    ComRuntime::global().call_sync_ephemeral_labeled(model, label, f)
+}
+
+/// Like [`call_sync_ephemeral`], returning an error instead of panicking
+/// when the task cannot be run.
+pub fn try_call_sync_ephemeral<F, R>(model: ComModel, f: F) -> Result<R, CallError>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    ComRuntime::global().try_call_sync_ephemeral(model, f)
+}
+
+impl ComRuntime {
+    /// See [`call_sync_ephemeral`].
+    pub fn call_sync_ephemeral<F, R>(&self, model: ComModel, f: F) -> R
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Any + Send + 'static,
+    {
+        self.call_sync_ephemeral_labeled(model, std::any::type_name::<F>(), f)
+    }
+
+    /// See [`call_sync_ephemeral_labeled`].
+    pub fn call_sync_ephemeral_labeled<F, R>(&self, model: ComModel, label: &'static str, f: F) -> R
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Any + Send + 'static,
+    {
+        dispatch_ephemeral(self, model, label, f).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// See [`try_call_sync_ephemeral`].
+    pub fn try_call_sync_ephemeral<F, R>(&self, model: ComModel, f: F) -> Result<R, CallError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Any + Send + 'static,
+    {
+        dispatch_ephemeral(self, model, std::any::type_name::<F>(), f)
+    }
+}
+
+fn dispatch_ephemeral<F, R>(
+    rt: &ComRuntime,
+    model: ComModel,
+    label: &'static str,
+    f: F,
+) -> Result<R, CallError>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    let (resp_tx, resp_rx) = mpsc::channel::<Reply>();
+    let msg = Message::Sync(Box::new(TaskImpl { f: Some(f), label }), resp_tx);
+    let apartment = rt.submit_with(model, msg, run)?;
+
+    match resp_rx.recv() {
+        Ok(reply) => Ok(unpack(reply)),
+        Err(_) => Err(apartment.lost()),
+    }
+}
+
+/// Hand `msg` to a spare thread, or a new one if none is left.
+fn run(apartment: &Arc<Apartment>, mut msg: Message) -> Result<(), CallError> {
+    loop {
+        let Some(spare) = apartment.spares.take() else {
+            let _ = spawn(apartment).send(msg);
+            break;
+        };
+        // a spare whose COM initialization failed has already exited
+        match spare.send(msg) {
+            Ok(()) => break,
+            Err(mpsc::SendError(returned)) => msg = returned,
+        }
+    }
+    refill(apartment);
+    Ok(())
+}
+
+/// Bring the spare threads of `apartment` to the configured count.
+pub(crate) fn refill(apartment: &Arc<Apartment>) {
+    let target = apartment.config().ephemeral_spares;
+    let mut inner = apartment.spares.inner.lock().unwrap();
+    if inner.closed {
+        return;
+    }
+    inner.senders.truncate(target);
+    while inner.senders.len() < target {
+        inner.senders.push(spawn(apartment));
+    }
+}
+
+/// Start a thread that initializes COM, runs the one message it receives,
+/// and exits.
+fn spawn(apartment: &Arc<Apartment>) -> mpsc::Sender<Message> {
+    let (tx, rx) = mpsc::channel::<Message>();
+    let model = apartment.model;
+    let apartment = Arc::downgrade(apartment);
+
+    std::thread::spawn(move || {
+        #[cfg(feature = "fault-injection")]
+        if apartment
+            .upgrade()
+            .is_some_and(|a| a.faults.init_fails(model))
+        {
+            return;
+        }
+        let hr = unsafe { windows::Win32::System::Com::CoInitializeEx(None, model.coinit()) };
+        if hr.is_err() {
+            return;
+        }
+        let _guard = ComGuard::entered(model);
+        crate::current::mark_worker();
+
+        let Ok(msg) = rx.recv() else {
+            return;
+        };
+        let panicked = msg.run();
+        if !panicked && let Some(apartment) = apartment.upgrade() {
+            apartment.completed.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+    tx
+}
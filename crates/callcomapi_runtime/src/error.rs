@@ -0,0 +1,76 @@
+use std::fmt;
+
+use windows::core::HRESULT;
+
+use crate::{CircuitOpen, ComModel};
+
+const E_FAIL: HRESULT = HRESULT(0x8000_4005_u32 as i32);
+const RPC_E_DISCONNECTED: HRESULT = HRESULT(0x8001_0108_u32 as i32);
+
+/// Why a task could not be run on its apartment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CallError {
+    /// The apartment exhausted its restart policy and no longer accepts work.
+    ApartmentFailed(ComModel),
+    /// The worker went away without replying.
+    WorkerLost(ComModel),
+    /// The runtime owning the apartment has been shut down.
+    ShutDown(ComModel),
+    /// The task panicked. Only reported to completion callbacks; other calls
+    /// re-raise the panic.
+    Panicked(ComModel),
+    /// The apartment already has a worker or another host thread, see
+    /// [`ComRuntime::host_apartment`](crate::ComRuntime::host_apartment).
+    AlreadyServed(ComModel),
+    /// The apartment's circuit breaker is open, see
+    /// [`ApartmentConfig::circuit_breaker`](crate::ApartmentConfig::circuit_breaker).
+    CircuitOpen(ComModel),
+    /// COM could not be initialized for the apartment on the calling thread,
+    /// with the `HRESULT` of `CoInitializeEx`; `RPC_E_CHANGED_MODE` when the
+    /// thread is already in the other model.
+    ComInit(ComModel, HRESULT),
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallError::ApartmentFailed(model) => write!(f, "{model:?} apartment has failed"),
+            CallError::WorkerLost(model) => write!(f, "{model:?} worker exited before replying"),
+            CallError::ShutDown(model) => write!(f, "{model:?} apartment has been shut down"),
+            CallError::Panicked(model) => write!(f, "task on the {model:?} apartment panicked"),
+            CallError::AlreadyServed(model) => {
+                write!(f, "{model:?} apartment is already served by another thread")
+            }
+            CallError::CircuitOpen(model) => {
+                write!(f, "circuit breaker of the {model:?} apartment is open")
+            }
+            CallError::ComInit(model, code) => {
+                write!(
+                    f,
+                    "cannot initialize COM for the {model:?} apartment: {code}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for CallError {}
+
+/// For APIs returning `windows::core::Result`: `RPC_E_DISCONNECTED` for an
+/// apartment that is gone, [`CircuitOpen::CODE`] for an open breaker, the
+/// failed initialization's own code and `E_FAIL` otherwise, with the error's
+/// message.
+impl From<CallError> for windows::core::Error {
+    fn from(error: CallError) -> Self {
+        let code = match error {
+            CallError::ApartmentFailed(_) | CallError::WorkerLost(_) | CallError::ShutDown(_) => {
+                RPC_E_DISCONNECTED
+            }
+            CallError::CircuitOpen(_) => CircuitOpen::CODE,
+            CallError::ComInit(_, code) => code,
+            CallError::Panicked(_) | CallError::AlreadyServed(_) => E_FAIL,
+        };
+        windows::core::Error::new(code, error.to_string())
+    }
+}
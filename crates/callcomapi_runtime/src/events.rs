@@ -0,0 +1,51 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{ComModel, HungTask};
+
+/// Notable things happening inside the runtime.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum RuntimeEvent {
+    /// The watchdog flagged a task.
+    TaskHung(HungTask),
+    /// A dead worker is being replaced after `backoff`.
+    WorkerRestarted {
+        model: ComModel,
+        restarts: u64,
+        backoff: Duration,
+    },
+    /// A worker reached its recycle limit and handed its queue to a fresh one.
+    WorkerRecycled {
+        model: ComModel,
+        tasks: u64,
+        age: Duration,
+    },
+    /// The restart policy was exhausted; the apartment rejects new work.
+    ApartmentFailed { model: ComModel },
+}
+
+type EventHandler = Arc<dyn Fn(&RuntimeEvent) + Send + Sync>;
+
+static HANDLER: Mutex<Option<EventHandler>> = Mutex::new(None);
+
+/// Install a handler receiving every [`RuntimeEvent`], replacing any previous one.
+///
+/// The handler runs on whichever runtime thread raised the event and must not block.
+pub fn set_event_handler<F>(f: F)
+where
+    F: Fn(&RuntimeEvent) + Send + Sync + 'static,
+{
+    *HANDLER.lock().unwrap() = Some(Arc::new(f));
+}
+
+pub fn clear_event_handler() {
+    *HANDLER.lock().unwrap() = None;
+}
+
+pub(crate) fn emit(event: RuntimeEvent) {
+    let handler = HANDLER.lock().unwrap().clone();
+    if let Some(handler) = handler {
+        handler(&event);
+    }
+}
@@ -0,0 +1,180 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use crate::{CallError, ComModel, ComRuntime};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FaultKind {
+    InitFails,
+    WorkerDies,
+    SendFails(CallError),
+    Delay(Duration),
+    DropResult,
+}
+
+/// A scripted dispatcher failure, see [`ComRuntime::inject_fault`].
+///
+/// A fault fires once unless [`times`](Self::times) or
+/// [`always`](Self::always) say otherwise, and applies to both apartments
+/// unless restricted with [`on`](Self::on).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fault {
+    kind: FaultKind,
+    model: Option<ComModel>,
+    /// `None` fires forever.
+    times: Option<u32>,
+}
+
+impl Fault {
+    /// The next worker to start fails to initialize COM and exits, which the
+    /// restart policy treats like any other dead worker.
+    pub fn init_fails() -> Self {
+        Self::new(FaultKind::InitFails)
+    }
+
+    /// The worker dies right after taking the next task off the queue. That
+    /// task is lost (its caller sees [`CallError::WorkerLost`]); the rest of
+    /// the queue waits for the restarted worker.
+    pub fn worker_dies() -> Self {
+        Self::new(FaultKind::WorkerDies)
+    }
+
+    /// Submitting the next task fails with `error` before it is queued.
+    pub fn send_fails(error: CallError) -> Self {
+        Self::new(FaultKind::SendFails(error))
+    }
+
+    /// The next task starts `delay` late, on the worker and counted as
+    /// running, so the watchdog sees it.
+    pub fn delay(delay: Duration) -> Self {
+        Self::new(FaultKind::Delay(delay))
+    }
+
+    /// The next task runs but its result is thrown away; its caller sees
+    /// [`CallError::WorkerLost`].
+    pub fn drop_result() -> Self {
+        Self::new(FaultKind::DropResult)
+    }
+
+    /// Only affect the apartment for `model`.
+    pub fn on(mut self, model: ComModel) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    /// Fire `times` times before retiring.
+    pub fn times(mut self, times: u32) -> Self {
+        assert!(times > 0, "fault must fire at least once");
+        self.times = Some(times);
+        self
+    }
+
+    /// Fire every time until the guard is dropped.
+    pub fn always(mut self) -> Self {
+        self.times = None;
+        self
+    }
+
+    fn new(kind: FaultKind) -> Self {
+        Fault {
+            kind,
+            model: None,
+            times: Some(1),
+        }
+    }
+}
+
+/// Keeps an injected fault active. Dropping it removes the fault, whether
+/// or not it has fired.
+#[must_use = "the fault is removed when the guard is dropped"]
+pub struct FaultGuard {
+    faults: Weak<Faults>,
+    id: u64,
+}
+
+impl Drop for FaultGuard {
+    fn drop(&mut self) {
+        if let Some(faults) = self.faults.upgrade() {
+            faults
+                .active
+                .lock()
+                .unwrap()
+                .retain(|(id, _)| *id != self.id);
+        }
+    }
+}
+
+/// Faults injected into one runtime, consulted by its dispatcher and workers.
+#[derive(Default)]
+pub(crate) struct Faults {
+    next_id: AtomicU64,
+    active: Mutex<Vec<(u64, Fault)>>,
+}
+
+impl Faults {
+    /// Consume one firing of the first active fault on `model` for which
+    /// `matches` returns a value.
+    fn fire<T>(&self, model: ComModel, matches: impl Fn(FaultKind) -> Option<T>) -> Option<T> {
+        let mut active = self.active.lock().unwrap();
+        let index = active.iter().position(|(_, fault)| {
+            fault.model.is_none_or(|m| m == model) && matches(fault.kind).is_some()
+        })?;
+        let fault = &mut active[index].1;
+        let value = matches(fault.kind);
+        match &mut fault.times {
+            Some(1) => {
+                active.remove(index);
+            }
+            Some(times) => *times -= 1,
+            None => {}
+        }
+        value
+    }
+
+    pub(crate) fn init_fails(&self, model: ComModel) -> bool {
+        self.fire(model, |kind| (kind == FaultKind::InitFails).then_some(()))
+            .is_some()
+    }
+
+    pub(crate) fn worker_dies(&self, model: ComModel) -> bool {
+        self.fire(model, |kind| (kind == FaultKind::WorkerDies).then_some(()))
+            .is_some()
+    }
+
+    pub(crate) fn send_error(&self, model: ComModel) -> Option<CallError> {
+        self.fire(model, |kind| match kind {
+            FaultKind::SendFails(error) => Some(error),
+            _ => None,
+        })
+    }
+
+    pub(crate) fn delay(&self, model: ComModel) -> Option<Duration> {
+        self.fire(model, |kind| match kind {
+            FaultKind::Delay(delay) => Some(delay),
+            _ => None,
+        })
+    }
+
+    pub(crate) fn drop_result(&self, model: ComModel) -> bool {
+        self.fire(model, |kind| (kind == FaultKind::DropResult).then_some(()))
+            .is_some()
+    }
+}
+
+impl ComRuntime {
+    /// Inject `fault` into this runtime until the returned guard is dropped.
+    ///
+    /// Faults act on the runtime's dispatcher and workers, so tests should
+    /// inject them into their own [`ComRuntime`] to stay isolated from tests
+    /// running in parallel. Inline tasks only see [`Fault::send_fails`].
+    pub fn inject_fault(&self, fault: Fault) -> FaultGuard {
+        let faults = &self.shared.faults;
+        let id = faults.next_id.fetch_add(1, Ordering::Relaxed);
+        faults.active.lock().unwrap().push((id, fault));
+        FaultGuard {
+            faults: Arc::downgrade(faults),
+            id,
+        }
+    }
+}
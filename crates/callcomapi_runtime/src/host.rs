@@ -0,0 +1,174 @@
+use std::marker::PhantomData;
+use std::panic::{AssertUnwindSafe, catch_unwind, resume_unwind};
+use std::sync::{Arc, mpsc};
+
+use crate::apartment::Apartment;
+use crate::{CallError, ComGuard, ComModel, ComRuntime};
+
+/// Drives an apartment from an existing thread's own loop, such as a GUI
+/// message loop. See [`ComRuntime::host_apartment`].
+///
+/// Implemented for closures, so waking a Win32 loop can look like:
+///
+/// ```ignore
+/// let hwnd = SendHwnd(hwnd);
+/// let host = host_apartment(ComModel::STA, move || unsafe {
+///     PostMessageW(Some(hwnd.0), WM_APP_COM, WPARAM(0), LPARAM(0)).ok();
+/// })?;
+/// // in the window procedure, on WM_APP_COM:
+/// host.run_pending();
+/// ```
+pub trait ApartmentExecutor: Send + Sync {
+    /// Called from any thread when work is queued. Must not block; arrange
+    /// for [`HostedApartment::run_pending`] to be called on the hosting
+    /// thread soon.
+    fn wake(&self);
+}
+
+impl<F> ApartmentExecutor for F
+where
+    F: Fn() + Send + Sync,
+{
+    fn wake(&self) {
+        self()
+    }
+}
+
+/// The current thread serving as an apartment, until dropped.
+///
+/// Dropping it uninitializes COM on the thread and hands the apartment back
+/// to a spawned worker, which runs the tasks still queued.
+#[must_use = "the thread stops hosting the apartment when this is dropped"]
+pub struct HostedApartment {
+    apartment: Arc<Apartment>,
+    _com: ComGuard,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl HostedApartment {
+    /// Run every task queued so far on this thread and return how many ran.
+    ///
+    /// Panics inside tasks are delivered to their callers as usual and do
+    /// not unwind through here.
+    pub fn run_pending(&self) -> usize {
+        self.apartment.run_hosted()
+    }
+}
+
+impl Drop for HostedApartment {
+    fn drop(&mut self) {
+        self.apartment.detach();
+        crate::current::unmark_worker();
+    }
+}
+
+/// Make the current thread the `model` apartment of the global runtime.
+/// See [`ComRuntime::host_apartment`].
+///
+/// A runtime has a single apartment per model, so the global runtime can
+/// host at most one `STA` and one `MTA` thread, and only while no worker
+/// serves them. Host further threads on runtimes of their own.
+pub fn host_apartment<E>(model: ComModel, executor: E) -> Result<HostedApartment, CallError>
+where
+    E: ApartmentExecutor + 'static,
+{
+    ComRuntime::global().host_apartment(model, executor)
+}
+
+/// Run `f` with the current thread serving as the `STA` apartment of the
+/// global runtime. See [`ComRuntime::run_main_sta`].
+pub fn run_main_sta<F, R>(f: F) -> Result<R, CallError>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    ComRuntime::global().run_main_sta(f)
+}
+
+enum Signal<R> {
+    Wake,
+    Done(std::thread::Result<R>),
+}
+
+impl ComRuntime {
+    /// Make the current thread the `model` apartment of this runtime, with
+    /// tasks run from the thread's own loop instead of a spawned worker.
+    ///
+    /// COM is initialized on the thread; an existing initialization in the
+    /// same model is fine. `executor` is woken whenever a task is queued and
+    /// the loop then calls [`HostedApartment::run_pending`]. A hosted
+    /// apartment is never restarted, recycled or replaced by the watchdog.
+    /// Once the [`HostedApartment`] is dropped, a spawned worker takes over
+    /// again.
+    ///
+    /// Hosting takes over this runtime's one apartment for `model`; it does
+    /// not add an apartment next to it. It fails with
+    /// [`CallError::AlreadyServed`] once a task has started the apartment's
+    /// own worker or another thread hosts it, so host it before making other
+    /// calls to it. Each further hosted apartment needs a runtime of its own,
+    /// targeted with `#[com_thread(runtime = ...)]`; that also keeps a main
+    /// thread apart from the pooled `STA` worker.
+    ///
+    /// Fails with [`CallError::ComInit`] if the thread is already in the
+    /// other apartment.
+    pub fn host_apartment<E>(
+        &self,
+        model: ComModel,
+        executor: E,
+    ) -> Result<HostedApartment, CallError>
+    where
+        E: ApartmentExecutor + 'static,
+    {
+        let apartment = self.apartment(model)?;
+        let hr = unsafe { windows::Win32::System::Com::CoInitializeEx(None, model.coinit()) };
+        if hr.is_err() {
+            return Err(CallError::ComInit(model, hr));
+        }
+        let com = ComGuard::entered(model);
+        apartment.attach(Arc::new(executor))?;
+        crate::current::mark_worker();
+        Ok(HostedApartment {
+            apartment,
+            _com: com,
+            _not_send: PhantomData,
+        })
+    }
+
+    /// Serve this runtime's `STA` apartment on the calling thread while `f`
+    /// runs on a new thread, returning what `f` returns.
+    ///
+    /// Meant for `main` in GUI apps whose clipboard, drag-and-drop or shell
+    /// dialog work must happen on the main thread: every `STA` call made
+    /// while `f` runs, including `#[com_thread(STA)]` functions, executes
+    /// here. A panic in `f` is re-raised on the calling thread. Once `f`
+    /// returns, `STA` calls go to a spawned worker again.
+    ///
+    /// Fails without running `f` if the apartment cannot be hosted, see
+    /// [`host_apartment`](Self::host_apartment).
+    pub fn run_main_sta<F, R>(&self, f: F) -> Result<R, CallError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel::<Signal<R>>();
+        let waker = tx.clone();
+        let host = self.host_apartment(ComModel::STA, move || {
+            let _ = waker.send(Signal::Wake);
+        })?;
+
+        std::thread::spawn(move || {
+            let res = catch_unwind(AssertUnwindSafe(f));
+            let _ = tx.send(Signal::Done(res));
+        });
+        let res = loop {
+            match rx.recv().expect("the body thread reports its result") {
+                Signal::Wake => {
+                    host.run_pending();
+                }
+                Signal::Done(res) => break res,
+            }
+        };
+        drop(host);
+        Ok(res.unwrap_or_else(|payload| resume_unwind(payload)))
+    }
+}
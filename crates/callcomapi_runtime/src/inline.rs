@@ -0,0 +1,80 @@
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+use crate::Message;
+
+thread_local! {
+    /// Live [`InlineGuard`]s on this thread.
+    static GUARDS: Cell<usize> = const { Cell::new(0) };
+    /// Inline tasks currently executing on this thread.
+    static RUNNING: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Runs tasks submitted from the current thread inline, on every runtime,
+/// until dropped. See [`ComRuntime::set_inline`](crate::ComRuntime::set_inline).
+///
+/// Only the creating thread is affected, so tests running in parallel do
+/// not interfere with each other.
+#[must_use = "inline execution ends when the guard is dropped"]
+pub struct InlineGuard {
+    _not_send: PhantomData<*const ()>,
+}
+
+/// Run tasks submitted from the current thread inline while the returned
+/// guard is alive.
+pub fn inline_guard() -> InlineGuard {
+    GUARDS.set(GUARDS.get() + 1);
+    InlineGuard {
+        _not_send: PhantomData,
+    }
+}
+
+impl Drop for InlineGuard {
+    fn drop(&mut self) {
+        GUARDS.set(GUARDS.get() - 1);
+    }
+}
+
+pub(crate) fn guarded() -> bool {
+    GUARDS.get() > 0
+}
+
+/// Whether the current thread is executing an inline task.
+pub(crate) fn running() -> bool {
+    RUNNING.get() > 0
+}
+
+/// Drive `future` on the current thread. Unlike
+/// `futures::executor::block_on`, this may run inside another executor,
+/// which is where inline tasks submitted from async code end up.
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+/// Run `msg` on the current thread. Returns `true` if the task panicked.
+pub(crate) fn run(msg: Message) -> bool {
+    RUNNING.set(RUNNING.get() + 1);
+    let panicked = msg.run();
+    RUNNING.set(RUNNING.get() - 1);
+    panicked
+}
@@ -0,0 +1,135 @@
+use std::any::Any;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use futures::StreamExt;
+use futures::future::BoxFuture;
+use futures::stream::FuturesUnordered;
+
+use crate::{CallError, ComModel, ComRuntime, dispatch_async};
+
+/// A group of tasks spawned to any apartment and awaited together.
+///
+/// Results are yielded as tasks finish, regardless of apartment. A panic in
+/// a task is re-raised where its result is awaited, like
+/// [`call_async`](crate::call_async). Dropping the set cancels every task
+/// that has not started yet; tasks already running finish and their results
+/// are discarded.
+///
+/// ```ignore
+/// let mut set = ApartmentJoinSet::new();
+/// set.spawn(ComModel::STA, query_shell);
+/// set.spawn(ComModel::MTA, query_wmi);
+/// let first = set.select().await;
+/// ```
+pub struct ApartmentJoinSet<'rt, R> {
+    runtime: &'rt ComRuntime,
+    tasks: FuturesUnordered<BoxFuture<'static, (usize, Result<R, CallError>)>>,
+    spawned: usize,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl<R> ApartmentJoinSet<'static, R>
+where
+    R: Any + Send + 'static,
+{
+    /// An empty set spawning onto the global runtime.
+    pub fn new() -> Self {
+        ComRuntime::global().join_set()
+    }
+}
+
+impl<R> Default for ApartmentJoinSet<'static, R>
+where
+    R: Any + Send + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ComRuntime {
+    /// An empty [`ApartmentJoinSet`] spawning onto this runtime.
+    pub fn join_set<R>(&self) -> ApartmentJoinSet<'_, R>
+    where
+        R: Any + Send + 'static,
+    {
+        ApartmentJoinSet {
+            runtime: self,
+            tasks: FuturesUnordered::new(),
+            spawned: 0,
+            cancelled: Arc::default(),
+        }
+    }
+}
+
+impl<R> ApartmentJoinSet<'_, R>
+where
+    R: Any + Send + 'static,
+{
+    /// Queue `f` on the apartment for `model`.
+    pub fn spawn<F>(&mut self, model: ComModel, f: F)
+    where
+        F: FnOnce() -> R + Send + 'static,
+    {
+        self.spawn_labeled(model, std::any::type_name::<F>(), f)
+    }
+
+    /// Like [`spawn`](Self::spawn), with a label identifying the task in
+    /// stats and watchdog reports.
+    pub fn spawn_labeled<F>(&mut self, model: ComModel, label: &'static str, f: F)
+    where
+        F: FnOnce() -> R + Send + 'static,
+    {
+        let cancelled = self.cancelled.clone();
+        let done = dispatch_async(self.runtime, model, label, move || {
+            (!cancelled.load(Ordering::Acquire)).then(f)
+        });
+
+        let index = self.spawned;
+        self.spawned += 1;
+        self.tasks.push(Box::pin(async move {
+            let res = done
+                .await
+                .map(|r| r.expect("tasks are only cancelled with their set"));
+            (index, res)
+        }));
+    }
+
+    /// Tasks spawned but not yet joined.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Wait for the next task to finish. Returns `None` once every spawned
+    /// task has been joined.
+    pub async fn join_next(&mut self) -> Option<Result<R, CallError>> {
+        self.tasks.next().await.map(|(_, res)| res)
+    }
+
+    /// Wait for every task, returning the results in spawn order.
+    pub async fn join_all(mut self) -> Vec<Result<R, CallError>> {
+        let mut results = Vec::with_capacity(self.tasks.len());
+        while let Some(done) = self.tasks.next().await {
+            results.push(done);
+        }
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, res)| res).collect()
+    }
+
+    /// Wait for the first task to finish and cancel the ones that have not
+    /// started. Returns `None` if the set is empty.
+    pub async fn select(mut self) -> Option<Result<R, CallError>> {
+        self.join_next().await
+    }
+}
+
+impl<R> Drop for ApartmentJoinSet<'_, R> {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+}
@@ -0,0 +1,112 @@
+use std::any::Any;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::mpsc;
+
+use crate::{CallError, ComModel, ComRuntime, Message, Reply, TaskImpl, unpack};
+
+/// Run `f` on the apartment for `model` and block until it returns. Calls
+/// with equal keys run one at a time, in the order they were made.
+///
+/// With [`ApartmentConfig::keyed_workers`](crate::ApartmentConfig::keyed_workers)
+/// set, each key is served by one worker of a pool, so work on different
+/// keys (say, different remote hosts) runs in parallel. Otherwise keyed calls
+/// share the apartment's worker. Pool workers are restarted, recycled and
+/// watched for hangs like the apartment's own worker, and panics are handled
+/// like [`call_sync`](crate::call_sync).
+pub fn call_keyed<K, F, R>(model: ComModel, key: K, f: F) -> R
+where
+    K: Hash,
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    ComRuntime::global().call_keyed(model, key, f)
+}
+
+/// Like [`call_keyed`], with a label identifying the task.
+pub fn call_keyed_labeled<K, F, R>(model: ComModel, key: K, label: &'static str, f: F) -> R
+where
+    K: Hash,
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    ComRuntime::global().call_keyed_labeled(model, key, label, f)
+}
+
+/// Like [`call_keyed`], returning an error instead of panicking when the
+/// task cannot be run.
+pub fn try_call_keyed<K, F, R>(model: ComModel, key: K, f: F) -> Result<R, CallError>
+where
+    K: Hash,
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    ComRuntime::global().try_call_keyed(model, key, f)
+}
+
+/// Hash of a `#[com_thread(key = ...)]` expression, taken before the
+/// parameters move into the task.
+#[doc(hidden)]
+pub fn __key_hash<K: Hash + ?Sized>(key: &K) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl ComRuntime {
+    /// See [`call_keyed`].
+    pub fn call_keyed<K, F, R>(&self, model: ComModel, key: K, f: F) -> R
+    where
+        K: Hash,
+        F: FnOnce() -> R + Send + 'static,
+        R: Any + Send + 'static,
+    {
+        self.call_keyed_labeled(model, key, std::any::type_name::<F>(), f)
+    }
+
+    /// See [`call_keyed_labeled`].
+    pub fn call_keyed_labeled<K, F, R>(
+        &self,
+        model: ComModel,
+        key: K,
+        label: &'static str,
+        f: F,
+    ) -> R
+    where
+        K: Hash,
+        F: FnOnce() -> R + Send + 'static,
+        R: Any + Send + 'static,
+    {
+        dispatch_keyed(self, model, __key_hash(&key), label, f).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// See [`try_call_keyed`].
+    pub fn try_call_keyed<K, F, R>(&self, model: ComModel, key: K, f: F) -> Result<R, CallError>
+    where
+        K: Hash,
+        F: FnOnce() -> R + Send + 'static,
+        R: Any + Send + 'static,
+    {
+        dispatch_keyed(self, model, __key_hash(&key), std::any::type_name::<F>(), f)
+    }
+}
+
+fn dispatch_keyed<F, R>(
+    rt: &ComRuntime,
+    model: ComModel,
+    hash: u64,
+    label: &'static str,
+    f: F,
+) -> Result<R, CallError>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    let (resp_tx, resp_rx) = mpsc::channel::<Reply>();
+    let msg = Message::Sync(Box::new(TaskImpl { f: Some(f), label }), resp_tx);
+    let apartment = rt.submit_with(model, msg, |apartment, msg| apartment.send_keyed(hash, msg))?;
+
+    match resp_rx.recv() {
+        Ok(reply) => Ok(unpack(reply)),
+        Err(_) => Err(apartment.lost()),
+    }
+}
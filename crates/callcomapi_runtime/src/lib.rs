@@ -1,194 +1,478 @@
 use std::any::Any;
-use std::collections::HashMap;
-use std::sync::{Mutex, OnceLock, mpsc};
+use std::panic::{AssertUnwindSafe, catch_unwind, resume_unwind};
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg(not(feature = "tokio"))]
+use futures::channel::oneshot;
+#[cfg(feature = "tokio")]
+use tokio::sync::oneshot;
+
+mod apartment;
+mod batch;
+mod cache;
+mod callback;
+mod circuit;
+mod config;
+mod context;
+mod current;
+mod detached;
+mod ephemeral;
+mod error;
+mod events;
+#[cfg(feature = "fault-injection")]
+mod faults;
+mod host;
+mod inline;
+mod join;
+mod keyed;
+mod reentrant;
+mod retry;
+mod runtime;
+mod scoped;
+mod session;
+mod single_flight;
+mod stats;
+mod stream;
+mod timer;
+#[cfg(feature = "tokio")]
+mod tokio_rt;
+mod watchdog;
+
+pub use apartment::ApartmentState;
+pub use batch::{call_batch, call_batch_async};
+pub use cache::{
+    ResultCache, call_cached, call_cached_async, call_cached_async_labeled, call_cached_labeled,
+};
+pub use callback::{
+    CompletionExecutor, PendingCall, call_with_callback, call_with_executor, start_call,
+};
+pub use circuit::{
+    CircuitBreaker, CircuitOpen, CircuitPolicy, CircuitState, call_with_breaker,
+    call_with_breaker_async, call_with_breaker_async_labeled, call_with_breaker_labeled,
+};
+pub use config::{ApartmentConfig, RestartPolicy, configure_apartment};
+pub use context::{add_context_hook, clear_context_hooks};
+#[doc(hidden)]
+pub use current::__assert_apartment;
+pub use current::{current_apartment, is_runtime_worker};
+pub use detached::{DetachedError, set_error_sink, spawn_detached, spawn_detached_labeled};
+pub use ephemeral::{call_sync_ephemeral, call_sync_ephemeral_labeled, try_call_sync_ephemeral};
+pub use error::CallError;
+pub use events::{RuntimeEvent, clear_event_handler, set_event_handler};
+#[cfg(feature = "fault-injection")]
+pub use faults::{Fault, FaultGuard};
+pub use host::{ApartmentExecutor, HostedApartment, host_apartment, run_main_sta};
+pub use inline::{InlineGuard, inline_guard};
+pub use join::ApartmentJoinSet;
+#[doc(hidden)]
+pub use keyed::__key_hash;
+pub use keyed::{call_keyed, call_keyed_labeled, try_call_keyed};
+pub use reentrant::{CallerHandle, call_sync_reentrant, try_call_sync_reentrant};
+pub use retry::{
+    RetryPolicy, call_with_retry, call_with_retry_async, call_with_retry_async_labeled,
+    call_with_retry_labeled, is_transient_hresult,
+};
+pub use runtime::ComRuntime;
+pub use scoped::{call_sync_scoped, call_sync_scoped_labeled, try_call_sync_scoped};
+pub use session::{
+    Session, SessionClosed, SessionReceiver, SessionSender, open_session, open_session_labeled,
+    try_open_session,
+};
+pub use single_flight::{
+    call_single_flight, call_single_flight_async, call_single_flight_async_labeled,
+    call_single_flight_labeled,
+};
+pub use stats::{
+    ApartmentStats, BreakerStats, CacheStats, CircuitStats, RunningTaskStats, RuntimeStats, stats,
+};
+pub use stream::{ApartmentStream, StreamClosed, StreamSink, call_stream, call_stream_labeled};
+pub use timer::{ScheduledTask, schedule_after, schedule_every};
+#[cfg(feature = "tokio")]
+pub use tokio_rt::TokioMode;
+pub use watchdog::{HungTask, HungTaskPolicy, WatchdogConfig, disable_watchdog, enable_watchdog};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ComModel {
     STA,
     MTA,
 }
 
+impl ComModel {
+    fn coinit(self) -> windows::Win32::System::Com::COINIT {
+        use windows::Win32::System::Com::{COINIT_APARTMENTTHREADED, COINIT_MULTITHREADED};
+        match self {
+            ComModel::STA => COINIT_APARTMENTTHREADED,
+            ComModel::MTA => COINIT_MULTITHREADED,
+        }
+    }
+}
+
 /// Helper for `with_com` macro to ensure COM cleanup
-pub struct ComGuard;
+pub struct ComGuard {
+    /// Set when this guard's initialization succeeded and must be undone.
+    model: Option<ComModel>,
+}
+
+impl ComGuard {
+    /// Record that COM was initialized for `model` on this thread.
+    fn entered(model: ComModel) -> Self {
+        current::push(model);
+        ComGuard { model: Some(model) }
+    }
+}
 
 impl Drop for ComGuard {
     fn drop(&mut self) {
-        unsafe {
-            windows::Win32::System::Com::CoUninitialize();
+        if self.model.take().is_some() {
+            unsafe {
+                windows::Win32::System::Com::CoUninitialize();
+            }
+            current::pop();
         }
     }
 }
 
 /// Initialize COM and return a guard that will uninitialize on drop.
 ///
+/// If the thread is already initialized with a different model, the
+/// guard does nothing and the thread stays in its existing apartment.
+///
 /// # Safety
 /// This function calls CoInitializeEx internally.
 pub unsafe fn init_com(model: ComModel) -> ComGuard {
-    use windows::Win32::System::Com::{
-        COINIT_APARTMENTTHREADED, COINIT_MULTITHREADED, CoInitializeEx,
-    };
-    let mode = match model {
-        ComModel::STA => COINIT_APARTMENTTHREADED,
-        ComModel::MTA => COINIT_MULTITHREADED,
-    };
-    unsafe {
-        let _ = CoInitializeEx(None, mode);
+    let hr = unsafe { windows::Win32::System::Com::CoInitializeEx(None, model.coinit()) };
+    if hr.is_err() {
+        return ComGuard { model: None };
     }
-    ComGuard
+    ComGuard::entered(model)
 }
 
 /// Re-export block_on for macro usage
+///
+/// With the `tokio` feature, futures are driven by the worker's tokio runtime
+/// (see [`TokioMode`]) so tokio timers, channels and I/O work inside
+/// `#[com_thread]` bodies.
 pub fn block_on<F: std::future::Future>(future: F) -> F::Output {
-    futures::executor::block_on(future)
+    #[cfg(feature = "tokio")]
+    return tokio_rt::block_on(future);
+    #[cfg(not(feature = "tokio"))]
+    if inline::running() {
+        inline::block_on(future)
+    } else {
+        futures::executor::block_on(future)
+    }
 }
 
 trait Task: Send {
+    fn label(&self) -> &'static str;
     fn run(self: Box<Self>) -> Box<dyn Any + Send>;
+
+    /// Whether the watchdog may report the task as hung.
+    fn watched(&self) -> bool {
+        true
+    }
 }
 
-struct TaskImpl<F>(Option<F>);
+struct TaskImpl<F> {
+    f: Option<F>,
+    label: &'static str,
+}
 
 impl<F, R> Task for TaskImpl<F>
 where
-    F: FnOnce() -> R + Send + 'static,
+    F: FnOnce() -> R + Send,
     R: Any + Send + 'static,
 {
+    fn label(&self) -> &'static str {
+        self.label
+    }
+
     fn run(self: Box<Self>) -> Box<dyn Any + Send> {
         let this = *self;
-        let f = this.0.expect("task already taken");
+        let f = this.f.expect("task already taken");
         let r = f();
         Box::new(r)
     }
 }
 
+/// A task that holds its worker for as long as its caller keeps it open,
+/// like a session or a stream, and so is never reported as hung.
+struct Unwatched(Box<dyn Task>);
+
+impl Task for Unwatched {
+    fn label(&self) -> &'static str {
+        self.0.label()
+    }
+
+    fn run(self: Box<Self>) -> Box<dyn Any + Send> {
+        self.0.run()
+    }
+
+    fn watched(&self) -> bool {
+        false
+    }
+}
+
+/// A task's return value, or the payload it panicked with.
+type Reply = std::thread::Result<Box<dyn Any + Send>>;
+
 enum Message {
-    Sync(Box<dyn Task>, std::sync::mpsc::Sender<Box<dyn Any + Send>>),
-    Async(
-        Box<dyn Task>,
-        futures::channel::oneshot::Sender<Box<dyn Any + Send>>,
-    ),
-}
-
-static THREAD_MAP: OnceLock<Mutex<HashMap<ComModel, mpsc::Sender<Message>>>> = OnceLock::new();
-
-fn ensure_sender(model: ComModel) -> mpsc::Sender<Message> {
-    let map_mutex = THREAD_MAP.get_or_init(|| Mutex::new(HashMap::new()));
-    let mut map = map_mutex.lock().unwrap();
-    if let Some(s) = map.get(&model) {
-        return s.clone();
-    }
-
-    let (s, r) = mpsc::channel::<Message>();
-
-    // spawn background thread
-    std::thread::spawn(move || {
-        // initialize COM for this thread if windows is available
-        #[allow(unused_unsafe)]
-        unsafe {
-            let _ = windows::Win32::System::Com::CoInitializeEx(
-                None,
-                match model {
-                    ComModel::MTA => windows::Win32::System::Com::COINIT_MULTITHREADED,
-                    ComModel::STA => windows::Win32::System::Com::COINIT_APARTMENTTHREADED,
-                },
-            );
+    /// The task is dropped before the reply sender, so a caller that sees its
+    /// reply channel close knows the task is gone (relied on by scoped calls).
+    Sync(Box<dyn Task>, std::sync::mpsc::Sender<Reply>),
+    Async(Box<dyn Task>, oneshot::Sender<Reply>),
+    /// Fire-and-forget; failures go to the detached error sink.
+    Detached(Box<dyn Task>),
+    /// Nothing to run; makes a waiting worker look at its timers again.
+    Wake,
+}
+
+impl Message {
+    fn label(&self) -> &'static str {
+        match self {
+            Message::Sync(task, _) | Message::Async(task, _) | Message::Detached(task) => {
+                task.label()
+            }
+            Message::Wake => "callcomapi::wake",
         }
+    }
 
-        struct ComGuard;
-        impl Drop for ComGuard {
-            fn drop(&mut self) {
-                unsafe {
-                    windows::Win32::System::Com::CoUninitialize();
-                }
+    fn watched(&self) -> bool {
+        match self {
+            Message::Sync(task, _) | Message::Async(task, _) | Message::Detached(task) => {
+                task.watched()
             }
+            Message::Wake => true,
         }
-        let _guard = ComGuard;
+    }
 
-        for msg in r {
-            match msg {
-                Message::Sync(task, resp_tx) => {
-                    let res = task.run();
-                    let _ = resp_tx.send(res);
-                }
-                Message::Async(task, resp_tx) => {
-                    let res = task.run();
-                    let _ = resp_tx.send(res);
+    /// Replace the task with `wrap(task)`, keeping the reply channel.
+    fn wrap(self, wrap: impl FnOnce(Box<dyn Task>) -> Box<dyn Task>) -> Self {
+        match self {
+            Message::Sync(task, resp_tx) => Message::Sync(wrap(task), resp_tx),
+            Message::Async(task, resp_tx) => Message::Async(wrap(task), resp_tx),
+            Message::Detached(task) => Message::Detached(wrap(task)),
+            Message::Wake => Message::Wake,
+        }
+    }
+
+    /// Make the task enter `handle` while it runs.
+    #[cfg(feature = "tokio")]
+    fn enter_handle(self, handle: tokio::runtime::Handle) -> Self {
+        self.wrap(|task| Box::new(tokio_rt::CallerHandleTask { task, handle }))
+    }
+
+    /// Swap the reply channel for one nobody listens on. The original sender
+    /// is handed back so it can be dropped once the task is gone.
+    #[cfg(feature = "fault-injection")]
+    fn divert_reply(self) -> (Self, Option<Box<dyn Any + Send>>) {
+        match self {
+            Message::Sync(task, resp_tx) => {
+                let (tx, _) = std::sync::mpsc::channel();
+                (Message::Sync(task, tx), Some(Box::new(resp_tx)))
+            }
+            Message::Async(task, resp_tx) => {
+                let (tx, _) = oneshot::channel();
+                (Message::Async(task, tx), Some(Box::new(resp_tx)))
+            }
+            msg @ (Message::Detached(_) | Message::Wake) => (msg, None),
+        }
+    }
+
+    /// Drop a message that will never run. Reply channels close on drop;
+    /// detached tasks have nobody waiting, so report them instead.
+    fn abandon(self, error: CallError) {
+        if let Message::Detached(task) = self {
+            detached::report(DetachedError::Call {
+                label: task.label(),
+                error,
+            });
+        }
+    }
+
+    /// Run the task and deliver its reply. Returns `true` if the task panicked.
+    fn run(self) -> bool {
+        match self {
+            Message::Sync(task, resp_tx) => {
+                let res = catch_unwind(AssertUnwindSafe(|| task.run()));
+                let panicked = res.is_err();
+                let _ = resp_tx.send(res);
+                panicked
+            }
+            Message::Async(task, resp_tx) => {
+                let res = catch_unwind(AssertUnwindSafe(|| task.run()));
+                let panicked = res.is_err();
+                let _ = resp_tx.send(res);
+                panicked
+            }
+            Message::Detached(task) => {
+                let label = task.label();
+                let res = catch_unwind(AssertUnwindSafe(|| task.run()));
+                match res {
+                    Ok(_) => false,
+                    Err(payload) => {
+                        detached::report(DetachedError::Panicked {
+                            label,
+                            message: detached::panic_message(&*payload),
+                        });
+                        true
+                    }
                 }
             }
+            Message::Wake => false,
         }
-        // thread ends when receiver is closed
-    });
+    }
+}
 
-    map.insert(model, s.clone());
-    s
+/// Unpack a reply, re-raising the task's panic on the calling thread.
+fn unpack<R: Any>(reply: Reply) -> R {
+    match reply {
+        Ok(boxed) => *boxed
+            .downcast::<R>()
+            .expect("type mismatch in runtime result"),
+        Err(payload) => resume_unwind(payload),
+    }
 }
 
+/// Run `f` on the apartment for `model` and block until it returns.
+///
+/// A panic inside `f` is re-raised on the calling thread. Panics with the
+/// [`CallError`] message if the apartment cannot run the task; use
+/// [`try_call_sync`] to handle that case.
+///
+/// Uses the [global](ComRuntime::global) runtime, as do the other free
+/// functions of this crate.
 pub fn call_sync<F, R>(model: ComModel, f: F) -> R
 where
     F: FnOnce() -> R + Send + 'static,
     R: Any + Send + 'static,
 {
-    let (resp_tx, resp_rx) = std::sync::mpsc::channel::<Box<dyn Any + Send>>();
-    let task: Box<dyn Task> = Box::new(TaskImpl(Some(f)));
-
-    // If the background thread has exited the receiver will be closed and
-    // send will return Err(msg). In that case we retry once by acquiring a
-    // fresh sender from `ensure_sender` and resending the message.
-    let mut msg = Message::Sync(task, resp_tx);
-    let mut sent = false;
-    for _ in 0..2 {
-        let sender = ensure_sender(model);
-        match sender.send(msg) {
-            Ok(()) => {
-                sent = true;
-                break;
-            }
-            Err(e) => {
-                // take back ownership of the message and retry
-                msg = e.0;
-            }
-        }
-    }
-    if !sent {
-        panic!("failed to send task to COM thread after retry");
-    }
+    ComRuntime::global().call_sync(model, f)
+}
 
-    let boxed = resp_rx.recv().expect("COM thread closed");
-    *boxed
-        .downcast::<R>()
-        .expect("type mismatch in runtime result")
+/// Like [`call_sync`], with a label identifying the task in stats and
+/// watchdog reports.
+pub fn call_sync_labeled<F, R>(model: ComModel, label: &'static str, f: F) -> R
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    ComRuntime::global().call_sync_labeled(model, label, f)
 }
 
+/// Like [`call_sync`], returning an error instead of panicking when the
+/// apartment cannot run the task.
+pub fn try_call_sync<F, R>(model: ComModel, f: F) -> Result<R, CallError>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    ComRuntime::global().try_call_sync(model, f)
+}
+
+/// Run `f` on the apartment for `model`, resolving once it returns.
+///
+/// Panic behaviour matches [`call_sync`]; use [`try_call_async`] to handle
+/// apartment failures.
 pub fn call_async<F, R>(model: ComModel, f: F) -> impl std::future::Future<Output = R>
 where
     F: FnOnce() -> R + Send + 'static,
     R: Any + Send + 'static,
 {
-    let (resp_tx, resp_rx) = futures::channel::oneshot::channel::<Box<dyn Any + Send>>();
-    let task: Box<dyn Task> = Box::new(TaskImpl(Some(f)));
-
-    let mut msg = Message::Async(task, resp_tx);
-    let mut sent = false;
-    for _ in 0..2 {
-        let sender = ensure_sender(model);
-        match sender.send(msg) {
-            Ok(()) => {
-                sent = true;
-                break;
-            }
-            Err(e) => {
-                msg = e.0;
-            }
-        }
-    }
-    if !sent {
-        panic!("failed to send async task to COM thread after retry");
+    ComRuntime::global().call_async(model, f)
+}
+
+/// Like [`call_async`], with a label identifying the task in stats and
+/// watchdog reports.
+pub fn call_async_labeled<F, R>(
+    model: ComModel,
+    label: &'static str,
+    f: F,
+) -> impl std::future::Future<Output = R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    ComRuntime::global().call_async_labeled(model, label, f)
+}
+
+/// Like [`call_async`], returning an error instead of panicking when the
+/// apartment cannot run the task.
+pub fn try_call_async<F, R>(
+    model: ComModel,
+    f: F,
+) -> impl std::future::Future<Output = Result<R, CallError>>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    ComRuntime::global().try_call_async(model, f)
+}
+
+fn dispatch_sync<F, R>(
+    rt: &ComRuntime,
+    model: ComModel,
+    label: &'static str,
+    f: F,
+) -> Result<R, CallError>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    run_sync_task(rt, model, Box::new(TaskImpl { f: Some(f), label }))
+}
+
+/// Queue `task` and block until its reply arrives or its reply channel
+/// closes. Either way the task has run or been dropped when this returns.
+fn run_sync_task<R: Any>(
+    rt: &ComRuntime,
+    model: ComModel,
+    task: Box<dyn Task>,
+) -> Result<R, CallError> {
+    let (resp_tx, resp_rx) = std::sync::mpsc::channel::<Reply>();
+
+    // The apartment restarts dead workers according to its restart policy;
+    // sending only fails once that policy has been exhausted or the runtime
+    // has been shut down.
+    let apartment = rt.submit(model, Message::Sync(task, resp_tx))?;
+
+    match resp_rx.recv() {
+        Ok(reply) => Ok(unpack(reply)),
+        Err(_) => Err(apartment.lost()),
     }
+}
+
+fn dispatch_async<F, R>(
+    rt: &ComRuntime,
+    model: ComModel,
+    label: &'static str,
+    f: F,
+) -> impl std::future::Future<Output = Result<R, CallError>> + use<F, R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    dispatch_task_async(rt, model, Box::new(TaskImpl { f: Some(f), label }))
+}
+
+/// Like [`dispatch_async`], for a task built by the caller that returns `R`.
+fn dispatch_task_async<R>(
+    rt: &ComRuntime,
+    model: ComModel,
+    task: Box<dyn Task>,
+) -> impl std::future::Future<Output = Result<R, CallError>> + use<R>
+where
+    R: Any + Send + 'static,
+{
+    let (resp_tx, resp_rx) = oneshot::channel::<Reply>();
+
+    // the task is queued eagerly, before the returned future is polled
+    let sent = rt.submit(model, Message::Async(task, resp_tx));
 
     async move {
-        let boxed = resp_rx.await.expect("COM thread closed");
-        *boxed
-            .downcast::<R>()
-            .expect("type mismatch in runtime result")
+        let apartment = sent?;
+        match resp_rx.await {
+            Ok(reply) => Ok(unpack(reply)),
+            Err(_) => Err(apartment.lost()),
+        }
     }
 }
@@ -0,0 +1,132 @@
+use std::any::Any;
+use std::panic::{AssertUnwindSafe, catch_unwind, resume_unwind};
+use std::sync::mpsc;
+use std::thread::{self, ThreadId};
+
+use crate::{CallError, ComModel, ComRuntime, Message, Reply, TaskImpl, unpack};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Lets a task started with [`call_sync_reentrant`] run closures on the
+/// thread waiting for it.
+pub struct CallerHandle {
+    tx: mpsc::Sender<Job>,
+    caller: ThreadId,
+}
+
+impl CallerHandle {
+    /// Run `f` on the caller's thread and wait for its result.
+    ///
+    /// The apartment stays blocked meanwhile, so `f` must not call back into
+    /// it. A panic in `f` is re-raised here, on the worker.
+    pub fn call<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        // inline tasks already run on the caller's thread
+        if thread::current().id() == self.caller {
+            return f();
+        }
+        let (res_tx, res_rx) = mpsc::channel();
+        let job: Job = Box::new(move || {
+            let _ = res_tx.send(catch_unwind(AssertUnwindSafe(f)));
+        });
+        self.tx
+            .send(job)
+            .expect("the caller waits until the task is done");
+        match res_rx.recv().expect("the caller runs every callback") {
+            Ok(value) => value,
+            Err(payload) => resume_unwind(payload),
+        }
+    }
+}
+
+/// Run `f` on the apartment for `model` and block until it returns, running
+/// the closures `f` sends through its [`CallerHandle`] on this thread in
+/// the meantime.
+///
+/// For exchanges where the worker needs the caller, such as showing a
+/// prompt, without deadlocking on the blocked caller:
+///
+/// ```ignore
+/// let saved = call_sync_reentrant(ComModel::STA, |caller| {
+///     let doc = open_document()?;
+///     if doc.is_dirty() && caller.call(|| confirm("Save changes?")) {
+///         doc.save()?;
+///     }
+///     Ok(())
+/// });
+/// ```
+///
+/// Panics and errors are handled like [`call_sync`](crate::call_sync).
+pub fn call_sync_reentrant<F, R>(model: ComModel, f: F) -> R
+where
+    F: FnOnce(&CallerHandle) -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    ComRuntime::global().call_sync_reentrant(model, f)
+}
+
+/// Like [`call_sync_reentrant`], returning an error instead of panicking
+/// when the task cannot be run.
+pub fn try_call_sync_reentrant<F, R>(model: ComModel, f: F) -> Result<R, CallError>
+where
+    F: FnOnce(&CallerHandle) -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    ComRuntime::global().try_call_sync_reentrant(model, f)
+}
+
+impl ComRuntime {
+    /// See [`call_sync_reentrant`].
+    pub fn call_sync_reentrant<F, R>(&self, model: ComModel, f: F) -> R
+    where
+        F: FnOnce(&CallerHandle) -> R + Send + 'static,
+        R: Any + Send + 'static,
+    {
+        dispatch_reentrant(self, model, f).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// See [`try_call_sync_reentrant`].
+    pub fn try_call_sync_reentrant<F, R>(&self, model: ComModel, f: F) -> Result<R, CallError>
+    where
+        F: FnOnce(&CallerHandle) -> R + Send + 'static,
+        R: Any + Send + 'static,
+    {
+        dispatch_reentrant(self, model, f)
+    }
+}
+
+fn dispatch_reentrant<F, R>(rt: &ComRuntime, model: ComModel, f: F) -> Result<R, CallError>
+where
+    F: FnOnce(&CallerHandle) -> R + Send + 'static,
+    R: Any + Send + 'static,
+{
+    let (job_tx, job_rx) = mpsc::channel::<Job>();
+    let handle = CallerHandle {
+        tx: job_tx,
+        caller: thread::current().id(),
+    };
+    // the handle goes away with the task, however it ends
+    let task = move || f(&handle);
+    let label = std::any::type_name::<F>();
+
+    let (resp_tx, resp_rx) = mpsc::channel::<Reply>();
+    let msg = Message::Sync(
+        Box::new(TaskImpl {
+            f: Some(task),
+            label,
+        }),
+        resp_tx,
+    );
+    let apartment = rt.submit(model, msg)?;
+
+    for job in job_rx {
+        job();
+    }
+    match resp_rx.recv() {
+        Ok(reply) => Ok(unpack(reply)),
+        Err(_) => Err(apartment.lost()),
+    }
+}
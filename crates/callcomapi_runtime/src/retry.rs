@@ -0,0 +1,265 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::channel::oneshot;
+use windows::core::HRESULT;
+
+use crate::{CallError, ComModel, ComRuntime, dispatch_async};
+
+const RPC_E_CALL_REJECTED: HRESULT = HRESULT(0x8001_0001_u32 as i32);
+const RPC_E_SERVERCALL_RETRYLATER: HRESULT = HRESULT(0x8001_010A_u32 as i32);
+/// `HRESULT_FROM_WIN32(RPC_S_SERVER_UNAVAILABLE)`
+const RPC_S_SERVER_UNAVAILABLE: HRESULT = HRESULT(0x8007_06BA_u32 as i32);
+/// `HRESULT_FROM_WIN32(RPC_S_SERVER_TOO_BUSY)`
+const RPC_S_SERVER_TOO_BUSY: HRESULT = HRESULT(0x8007_06BB_u32 as i32);
+
+/// The default classifier of [`RetryPolicy`]: `true` for failures of a
+/// busy or unreachable server that are worth trying again
+/// (`RPC_E_CALL_REJECTED`, `RPC_E_SERVERCALL_RETRYLATER`,
+/// `RPC_S_SERVER_UNAVAILABLE` and `RPC_S_SERVER_TOO_BUSY`).
+pub fn is_transient_hresult(code: HRESULT) -> bool {
+    matches!(
+        code,
+        RPC_E_CALL_REJECTED
+            | RPC_E_SERVERCALL_RETRYLATER
+            | RPC_S_SERVER_UNAVAILABLE
+            | RPC_S_SERVER_TOO_BUSY
+    )
+}
+
+/// How often and how soon a call failing with a retryable HRESULT is tried
+/// again, see [`call_with_retry`].
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_delay: Duration,
+    max_delay: Duration,
+    exponential: bool,
+    classify: fn(HRESULT) -> bool,
+}
+
+impl RetryPolicy {
+    /// Retry up to `max_retries` times without waiting.
+    pub fn immediate(max_retries: u32) -> Self {
+        Self::fixed(max_retries, Duration::ZERO)
+    }
+
+    /// Retry up to `max_retries` times, `delay` after each failure.
+    pub fn fixed(max_retries: u32, delay: Duration) -> Self {
+        RetryPolicy {
+            max_retries,
+            initial_delay: delay,
+            max_delay: delay,
+            exponential: false,
+            classify: is_transient_hresult,
+        }
+    }
+
+    /// Retry up to `max_retries` times, `initial` after the first failure,
+    /// doubling on each further one up to `max`.
+    pub fn exponential(max_retries: u32, initial: Duration, max: Duration) -> Self {
+        RetryPolicy {
+            max_retries,
+            initial_delay: initial,
+            max_delay: max,
+            exponential: true,
+            classify: is_transient_hresult,
+        }
+    }
+
+    /// Decide with `classify` which HRESULTs are retried, instead of
+    /// [`is_transient_hresult`].
+    pub fn classify(mut self, classify: fn(HRESULT) -> bool) -> Self {
+        self.classify = classify;
+        self
+    }
+
+    /// Whether the call should run again after failing `failures` times
+    /// (1-based) with `code`.
+    fn retries(&self, failures: u32, code: HRESULT) -> bool {
+        failures <= self.max_retries && (self.classify)(code)
+    }
+
+    /// Delay before the `retry`-th retry (1-based).
+    fn delay_for(&self, retry: u32) -> Duration {
+        if !self.exponential {
+            return self.initial_delay;
+        }
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+}
+
+/// Run `f` on the apartment for `model`, running it again while it fails
+/// with an HRESULT that `policy` retries.
+///
+/// Every attempt is queued as its own task and the delay in between is
+/// kept by the apartment's timers, so other calls run while a retry waits.
+/// Returns the first success, the first error that is not retried, or the
+/// last error once the retries are used up. A call the apartment cannot
+/// run, say after shutdown, fails with the [`CallError`] converted into a
+/// `windows::core::Error`. A panic in `f` is re-raised and not retried.
+///
+/// ```ignore
+/// let policy = RetryPolicy::exponential(3, Duration::from_millis(100), Duration::from_secs(2));
+/// let disks = call_with_retry(ComModel::MTA, policy, || wmi.query("SELECT * FROM Win32_DiskDrive"))?;
+/// ```
+pub fn call_with_retry<F, R>(model: ComModel, policy: RetryPolicy, f: F) -> windows::core::Result<R>
+where
+    F: Fn() -> windows::core::Result<R> + Send + Sync + 'static,
+    R: Send + 'static,
+{
+    ComRuntime::global().call_with_retry(model, policy, f)
+}
+
+/// Like [`call_with_retry`], with a label identifying the task in stats
+/// and watchdog reports.
+pub fn call_with_retry_labeled<F, R>(
+    model: ComModel,
+    policy: RetryPolicy,
+    label: &'static str,
+    f: F,
+) -> windows::core::Result<R>
+where
+    F: Fn() -> windows::core::Result<R> + Send + Sync + 'static,
+    R: Send + 'static,
+{
+    ComRuntime::global().call_with_retry_labeled(model, policy, label, f)
+}
+
+/// Async version of [`call_with_retry`].
+pub fn call_with_retry_async<F, R>(
+    model: ComModel,
+    policy: RetryPolicy,
+    f: F,
+) -> impl Future<Output = windows::core::Result<R>> + use<F, R>
+where
+    F: Fn() -> windows::core::Result<R> + Send + Sync + 'static,
+    R: Send + 'static,
+{
+    ComRuntime::global().call_with_retry_async(model, policy, f)
+}
+
+/// Async version of [`call_with_retry_labeled`].
+pub fn call_with_retry_async_labeled<F, R>(
+    model: ComModel,
+    policy: RetryPolicy,
+    label: &'static str,
+    f: F,
+) -> impl Future<Output = windows::core::Result<R>> + use<F, R>
+where
+    F: Fn() -> windows::core::Result<R> + Send + Sync + 'static,
+    R: Send + 'static,
+{
+    ComRuntime::global().call_with_retry_async_labeled(model, policy, label, f)
+}
+
+impl ComRuntime {
+    /// See [`call_with_retry`].
+    pub fn call_with_retry<F, R>(
+        &self,
+        model: ComModel,
+        policy: RetryPolicy,
+        f: F,
+    ) -> windows::core::Result<R>
+    where
+        F: Fn() -> windows::core::Result<R> + Send + Sync + 'static,
+        R: Send + 'static,
+    {
+        self.call_with_retry_labeled(model, policy, std::any::type_name::<F>(), f)
+    }
+
+    /// See [`call_with_retry_labeled`].
+    pub fn call_with_retry_labeled<F, R>(
+        &self,
+        model: ComModel,
+        policy: RetryPolicy,
+        label: &'static str,
+        f: F,
+    ) -> windows::core::Result<R>
+    where
+        F: Fn() -> windows::core::Result<R> + Send + Sync + 'static,
+        R: Send + 'static,
+    {
+        futures::executor::block_on(self.call_with_retry_async_labeled(model, policy, label, f))
+    }
+
+    /// See [`call_with_retry_async`].
+    pub fn call_with_retry_async<F, R>(
+        &self,
+        model: ComModel,
+        policy: RetryPolicy,
+        f: F,
+    ) -> impl Future<Output = windows::core::Result<R>> + use<F, R>
+    where
+        F: Fn() -> windows::core::Result<R> + Send + Sync + 'static,
+        R: Send + 'static,
+    {
+        self.call_with_retry_async_labeled(model, policy, std::any::type_name::<F>(), f)
+    }
+
+    /// See [`call_with_retry_async_labeled`].
+    pub fn call_with_retry_async_labeled<F, R>(
+        &self,
+        model: ComModel,
+        policy: RetryPolicy,
+        label: &'static str,
+        f: F,
+    ) -> impl Future<Output = windows::core::Result<R>> + use<F, R>
+    where
+        F: Fn() -> windows::core::Result<R> + Send + Sync + 'static,
+        R: Send + 'static,
+    {
+        let f = Arc::new(f);
+        let attempt = {
+            let f = f.clone();
+            move || f()
+        };
+        // the first attempt is queued eagerly, like other async calls
+        let first = dispatch_async(self, model, label, attempt);
+        let rt = self.handle();
+
+        async move {
+            let mut res = first.await;
+            let mut failures = 0;
+            loop {
+                let error = match res? {
+                    Ok(r) => return Ok(r),
+                    Err(error) => error,
+                };
+                failures += 1;
+                if !policy.retries(failures, error.code()) {
+                    return Err(error);
+                }
+                rt.retry_delay(model, policy.delay_for(failures)).await?;
+                let f = f.clone();
+                res = dispatch_async(&rt, model, label, move || f()).await;
+            }
+        }
+    }
+
+    /// Wait for `delay` on the apartment's timers, leaving its worker free.
+    async fn retry_delay(&self, model: ComModel, delay: Duration) -> Result<(), CallError> {
+        if delay.is_zero() {
+            return Ok(());
+        }
+        let (tx, rx) = oneshot::channel();
+        let mut tx = Some(tx);
+        crate::timer::schedule(
+            self,
+            model,
+            delay,
+            None,
+            "callcomapi::retry_delay",
+            move || {
+                if let Some(tx) = tx.take() {
+                    let _ = tx.send(());
+                }
+            },
+        )?;
+        // the timer is dropped unfired when the apartment closes
+        rx.await.map_err(|_| CallError::ShutDown(model))
+    }
+}
@@ -0,0 +1,296 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+
+use crate::apartment::Apartment;
+use crate::{ApartmentConfig, CallError, ComModel, Message, dispatch_async, dispatch_sync, inline};
+
+static GLOBAL: LazyLock<ComRuntime> = LazyLock::new(ComRuntime::new);
+
+/// A set of COM apartments with their own configuration, watchdog and
+/// shutdown, independent of every other runtime in the process.
+///
+/// The free functions of this crate and `#[com_thread]` use the
+/// [global](ComRuntime::global) instance. A library that wants to keep its
+/// apartments apart from the host application's can own one instead:
+///
+/// ```ignore
+/// static RUNTIME: LazyLock<ComRuntime> = LazyLock::new(ComRuntime::new);
+///
+/// #[com_thread(runtime = RUNTIME)]
+/// fn query() -> u32 {
+///     // runs on RUNTIME's STA worker
+/// }
+/// ```
+///
+/// Dropping a runtime shuts it down.
+pub struct ComRuntime {
+    pub(crate) shared: Arc<Shared>,
+    /// Whether dropping this value shuts the runtime down; not so for a
+    /// [`handle`](Self::handle).
+    owner: bool,
+}
+
+/// State reachable from the runtime's background threads.
+pub(crate) struct Shared {
+    apartments: Mutex<Apartments>,
+    /// Stop flag of the running watchdog thread.
+    pub(crate) watchdog: Mutex<Option<Arc<AtomicBool>>>,
+    inline: AtomicBool,
+    pub(crate) contexts: crate::context::ContextHooks,
+    pub(crate) completions: crate::callback::Completions,
+    pub(crate) flights: crate::single_flight::Flights,
+    pub(crate) caches: crate::cache::Caches,
+    pub(crate) breakers: crate::circuit::Breakers,
+    #[cfg(feature = "fault-injection")]
+    pub(crate) faults: Arc<crate::faults::Faults>,
+}
+
+#[derive(Default)]
+struct Apartments {
+    map: HashMap<ComModel, Arc<Apartment>>,
+    shut_down: bool,
+}
+
+impl Shared {
+    /// Snapshot of every apartment started so far.
+    pub(crate) fn apartments(&self) -> Vec<Arc<Apartment>> {
+        self.apartments
+            .lock()
+            .unwrap()
+            .map
+            .values()
+            .cloned()
+            .collect()
+    }
+}
+
+impl ComRuntime {
+    /// Create a runtime. Apartments are started on first use.
+    pub fn new() -> Self {
+        ComRuntime {
+            shared: Arc::new(Shared {
+                apartments: Mutex::new(Apartments::default()),
+                watchdog: Mutex::new(None),
+                inline: AtomicBool::new(false),
+                contexts: Default::default(),
+                completions: Default::default(),
+                flights: Default::default(),
+                caches: Default::default(),
+                breakers: Default::default(),
+                #[cfg(feature = "fault-injection")]
+                faults: Arc::default(),
+            }),
+            owner: true,
+        }
+    }
+
+    /// A reference to this runtime for futures that submit more work after
+    /// the call that created them has returned.
+    pub(crate) fn handle(&self) -> ComRuntime {
+        ComRuntime {
+            shared: self.shared.clone(),
+            owner: false,
+        }
+    }
+
+    /// The process-wide default runtime. It is never shut down.
+    pub fn global() -> &'static ComRuntime {
+        &GLOBAL
+    }
+
+    /// Return the apartment for `model`, spawning its worker on first use.
+    pub(crate) fn apartment(&self, model: ComModel) -> Result<Arc<Apartment>, CallError> {
+        let mut apartments = self.shared.apartments.lock().unwrap();
+        if apartments.shut_down {
+            return Err(CallError::ShutDown(model));
+        }
+        Ok(apartments
+            .map
+            .entry(model)
+            .or_insert_with(|| Apartment::start(model, &self.shared))
+            .clone())
+    }
+
+    /// Queue `msg` on the apartment for `model`, or run it right away when
+    /// tasks run inline.
+    pub(crate) fn submit(
+        &self,
+        model: ComModel,
+        msg: Message,
+    ) -> Result<Arc<Apartment>, CallError> {
+        self.submit_with(model, msg, Apartment::send)
+    }
+
+    /// Like [`submit`](Self::submit), handing `msg` to `dispatch` instead of
+    /// the apartment's queue.
+    pub(crate) fn submit_with(
+        &self,
+        model: ComModel,
+        msg: Message,
+        dispatch: impl FnOnce(&Arc<Apartment>, Message) -> Result<(), CallError>,
+    ) -> Result<Arc<Apartment>, CallError> {
+        let (apartment, msg) = self.prepare(model, msg)?;
+        if self.is_inline() {
+            apartment.run_inline(msg)?;
+        } else {
+            dispatch(&apartment, msg)?;
+        }
+        Ok(apartment)
+    }
+
+    /// Like [`submit`](Self::submit), but always queued for a worker, even
+    /// when tasks run inline.
+    pub(crate) fn submit_queued(
+        &self,
+        model: ComModel,
+        msg: Message,
+    ) -> Result<Arc<Apartment>, CallError> {
+        let (apartment, msg) = self.prepare(model, msg)?;
+        apartment.send(msg)?;
+        Ok(apartment)
+    }
+
+    fn prepare(
+        &self,
+        model: ComModel,
+        msg: Message,
+    ) -> Result<(Arc<Apartment>, Message), CallError> {
+        let apartment = self.apartment(model)?;
+        #[cfg(feature = "fault-injection")]
+        if let Some(error) = self.shared.faults.send_error(model) {
+            return Err(error);
+        }
+        if !apartment.circuit.admit() {
+            return Err(CallError::CircuitOpen(model));
+        }
+        let msg = apartment.circuit.guard(self.shared.contexts.capture(msg));
+        Ok((apartment, msg))
+    }
+
+    /// Run every task submitted to this runtime on the submitting thread,
+    /// in submission order, instead of on apartment workers.
+    ///
+    /// Meant for tests: `#[com_thread]` bodies can be stepped through in a
+    /// debugger and results do not depend on thread scheduling. COM is not
+    /// initialized for inline tasks. The `Send + 'static` bounds, stats and
+    /// panic propagation are unchanged; a panicking task does not count
+    /// against the restart policy. See [`inline_guard`](crate::inline_guard)
+    /// to enable this for the current thread only.
+    pub fn set_inline(&self, inline: bool) {
+        self.shared.inline.store(inline, Ordering::Relaxed);
+    }
+
+    /// Whether tasks submitted from the current thread run inline.
+    pub fn is_inline(&self) -> bool {
+        self.shared.inline.load(Ordering::Relaxed) || inline::guarded()
+    }
+
+    /// Apply `config` to the apartment for `model`, starting it if needed.
+    /// Has no effect once the runtime has been shut down.
+    pub fn configure_apartment(&self, model: ComModel, config: ApartmentConfig) {
+        if let Ok(apartment) = self.apartment(model) {
+            apartment.set_config(config);
+        }
+    }
+
+    /// Stop accepting work and stop this runtime's watchdog.
+    ///
+    /// Tasks already queued still run; each worker exits once its queue is
+    /// empty. New calls fail with [`CallError::ShutDown`]. Does not wait for
+    /// the workers.
+    pub fn shutdown(&self) {
+        self.disable_watchdog();
+        let apartments = {
+            let mut apartments = self.shared.apartments.lock().unwrap();
+            apartments.shut_down = true;
+            apartments.map.values().cloned().collect::<Vec<_>>()
+        };
+        for apartment in apartments {
+            apartment.close();
+        }
+    }
+
+    /// See [`call_sync`](crate::call_sync).
+    pub fn call_sync<F, R>(&self, model: ComModel, f: F) -> R
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Any + Send + 'static,
+    {
+        self.call_sync_labeled(model, std::any::type_name::<F>(), f)
+    }
+
+    /// See [`call_sync_labeled`](crate::call_sync_labeled).
+    pub fn call_sync_labeled<F, R>(&self, model: ComModel, label: &'static str, f: F) -> R
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Any + Send + 'static,
+    {
+        dispatch_sync(self, model, label, f).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// See [`try_call_sync`](crate::try_call_sync).
+    pub fn try_call_sync<F, R>(&self, model: ComModel, f: F) -> Result<R, CallError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Any + Send + 'static,
+    {
+        dispatch_sync(self, model, std::any::type_name::<F>(), f)
+    }
+
+    /// See [`call_async`](crate::call_async).
+    pub fn call_async<F, R>(
+        &self,
+        model: ComModel,
+        f: F,
+    ) -> impl std::future::Future<Output = R> + use<F, R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Any + Send + 'static,
+    {
+        self.call_async_labeled(model, std::any::type_name::<F>(), f)
+    }
+
+    /// See [`call_async_labeled`](crate::call_async_labeled).
+    pub fn call_async_labeled<F, R>(
+        &self,
+        model: ComModel,
+        label: &'static str,
+        f: F,
+    ) -> impl std::future::Future<Output = R> + use<F, R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Any + Send + 'static,
+    {
+        let fut = dispatch_async(self, model, label, f);
+        async move { fut.await.unwrap_or_else(|e| panic!("{e}")) }
+    }
+
+    /// See [`try_call_async`](crate::try_call_async).
+    pub fn try_call_async<F, R>(
+        &self,
+        model: ComModel,
+        f: F,
+    ) -> impl std::future::Future<Output = Result<R, CallError>> + use<F, R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Any + Send + 'static,
+    {
+        dispatch_async(self, model, std::any::type_name::<F>(), f)
+    }
+}
+
+impl Default for ComRuntime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ComRuntime {
+    fn drop(&mut self) {
+        if self.owner {
+            self.shutdown();
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::any::Any;
+
+use crate::{CallError, ComModel, ComRuntime, Task, TaskImpl, run_sync_task};
+
+/// Like [`call_sync`](crate::call_sync), but `f` may borrow from the caller's
+/// stack, in the spirit of [`std::thread::scope`].
+///
+/// The calling thread stays blocked until `f` has run or been dropped, so
+/// borrowed data outlives every use on the worker. The result still has to
+/// be `'static` since it is handed back through the apartment's reply channel.
+pub fn call_sync_scoped<'env, F, R>(model: ComModel, f: F) -> R
+where
+    F: FnOnce() -> R + Send + 'env,
+    R: Any + Send + 'static,
+{
+    ComRuntime::global().call_sync_scoped(model, f)
+}
+
+/// Like [`call_sync_scoped`], with a label identifying the task in stats and
+/// watchdog reports.
+pub fn call_sync_scoped_labeled<'env, F, R>(model: ComModel, label: &'static str, f: F) -> R
+where
+    F: FnOnce() -> R + Send + 'env,
+    R: Any + Send + 'static,
+{
+    ComRuntime::global().call_sync_scoped_labeled(model, label, f)
+}
+
+/// Like [`call_sync_scoped`], returning an error instead of panicking when
+/// the apartment cannot run the task.
+pub fn try_call_sync_scoped<'env, F, R>(model: ComModel, f: F) -> Result<R, CallError>
+where
+    F: FnOnce() -> R + Send + 'env,
+    R: Any + Send + 'static,
+{
+    ComRuntime::global().try_call_sync_scoped(model, f)
+}
+
+impl ComRuntime {
+    /// See [`call_sync_scoped`].
+    pub fn call_sync_scoped<'env, F, R>(&self, model: ComModel, f: F) -> R
+    where
+        F: FnOnce() -> R + Send + 'env,
+        R: Any + Send + 'static,
+    {
+        self.call_sync_scoped_labeled(model, std::any::type_name::<F>(), f)
+    }
+
+    /// See [`call_sync_scoped_labeled`].
+    pub fn call_sync_scoped_labeled<'env, F, R>(
+        &self,
+        model: ComModel,
+        label: &'static str,
+        f: F,
+    ) -> R
+    where
+        F: FnOnce() -> R + Send + 'env,
+        R: Any + Send + 'static,
+    {
+        dispatch_scoped(self, model, label, f).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// See [`try_call_sync_scoped`].
+    pub fn try_call_sync_scoped<'env, F, R>(&self, model: ComModel, f: F) -> Result<R, CallError>
+    where
+        F: FnOnce() -> R + Send + 'env,
+        R: Any + Send + 'static,
+    {
+        dispatch_scoped(self, model, std::any::type_name::<F>(), f)
+    }
+}
+
+fn dispatch_scoped<'env, F, R>(
+    rt: &ComRuntime,
+    model: ComModel,
+    label: &'static str,
+    f: F,
+) -> Result<R, CallError>
+where
+    F: FnOnce() -> R + Send + 'env,
+    R: Any + Send + 'static,
+{
+    let task: Box<dyn Task + 'env> = Box::new(TaskImpl { f: Some(f), label });
+    // SAFETY: `run_sync_task` does not return until the task has been run or
+    // dropped: the reply is sent after the task is consumed, and a message
+    // that never runs drops its task before its reply sender. Nothing
+    // borrowed for 'env is therefore touched after this function returns,
+    // including when the caller unwinds from a re-raised panic.
+    let task: Box<dyn Task + 'static> = unsafe { std::mem::transmute(task) };
+    run_sync_task(rt, model, task)
+}
@@ -0,0 +1,249 @@
+use std::fmt;
+use std::panic::resume_unwind;
+use std::sync::Arc;
+use std::sync::mpsc;
+
+use crate::apartment::Apartment;
+use crate::{CallError, ComModel, ComRuntime, Message, Reply, TaskImpl, Unwatched};
+
+/// The other side of a session has gone away.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SessionClosed;
+
+impl fmt::Display for SessionClosed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("session was closed")
+    }
+}
+
+impl std::error::Error for SessionClosed {}
+
+/// Worker-side receiver of the caller's requests.
+pub struct SessionReceiver<Req> {
+    rx: mpsc::Receiver<Req>,
+}
+
+impl<Req> SessionReceiver<Req> {
+    /// Wait for the next request. Returns `None` once the caller has closed
+    /// the session or dropped it.
+    pub fn recv(&self) -> Option<Req> {
+        self.rx.recv().ok()
+    }
+}
+
+impl<Req> Iterator for SessionReceiver<Req> {
+    type Item = Req;
+
+    fn next(&mut self) -> Option<Req> {
+        self.recv()
+    }
+}
+
+/// Worker-side sender of responses to the caller. Dropping it ends the
+/// caller's stream of responses.
+pub struct SessionSender<Resp> {
+    tx: mpsc::Sender<Resp>,
+}
+
+impl<Resp> SessionSender<Resp> {
+    /// Send a response. Fails once the caller has dropped the session.
+    pub fn send(&self, resp: Resp) -> Result<(), SessionClosed> {
+        self.tx.send(resp).map_err(|_| SessionClosed)
+    }
+}
+
+/// Caller-side end of a session opened with [`open_session`].
+///
+/// Dropping it closes both directions: the handler's
+/// [`SessionReceiver::recv`] returns `None` and its sends fail.
+pub struct Session<Req, Resp> {
+    // dropped first, so a handler seeing its requests end can no longer respond
+    responses: mpsc::Receiver<Resp>,
+    requests: Option<mpsc::Sender<Req>>,
+    done: Option<mpsc::Receiver<Reply>>,
+    apartment: Arc<Apartment>,
+}
+
+impl<Req, Resp> Session<Req, Resp> {
+    /// Send a request to the handler. Fails once the handler has returned
+    /// or the session was closed.
+    pub fn send(&self, req: Req) -> Result<(), SessionClosed> {
+        match &self.requests {
+            Some(tx) => tx.send(req).map_err(|_| SessionClosed),
+            None => Err(SessionClosed),
+        }
+    }
+
+    /// Wait for the next response. Returns `None` once the handler has
+    /// dropped its [`SessionSender`] or returned.
+    ///
+    /// A panic in the handler is re-raised here after its last response.
+    /// Panics with the [`CallError`] message if the handler could not run.
+    pub fn recv(&mut self) -> Option<Resp> {
+        match self.responses.recv() {
+            Ok(resp) => Some(resp),
+            Err(_) => {
+                self.finish();
+                None
+            }
+        }
+    }
+
+    /// Send `req` and wait for the response to it.
+    pub fn request(&mut self, req: Req) -> Option<Resp> {
+        self.send(req).ok()?;
+        self.recv()
+    }
+
+    /// Stop sending requests and wait for the handler to return, dropping
+    /// any responses it still sends. A panic in the handler is re-raised.
+    pub fn close(mut self) {
+        self.requests = None;
+        while self.responses.recv().is_ok() {}
+        self.finish();
+    }
+
+    /// Surface how the handler ended, once.
+    fn finish(&mut self) {
+        let Some(done) = self.done.take() else {
+            return;
+        };
+        match done.recv() {
+            Ok(Ok(_)) => {}
+            Ok(Err(payload)) => resume_unwind(payload),
+            Err(_) => panic!("{}", self.apartment.lost()),
+        }
+    }
+}
+
+/// Run `f` on the apartment for `model` as a long-lived handler exchanging
+/// requests and responses with the caller through the returned [`Session`].
+///
+/// ```ignore
+/// let mut doc = open_session(ComModel::STA, |requests, responses| {
+///     let word = Word::launch()?;
+///     for cmd in requests {
+///         responses.send(word.execute(cmd)).ok();
+///     }
+/// });
+/// let page_count = doc.request(Command::PageCount);
+/// doc.close();
+/// ```
+///
+/// The worker is occupied for the whole session, so other calls to the
+/// apartment wait until the handler returns; the watchdog does not count
+/// that as a hang. Sessions always run on a worker, even when tasks
+/// otherwise run inline. Panics with the [`CallError`] message if the
+/// handler cannot be queued, see [`try_open_session`].
+pub fn open_session<F, Req, Resp>(model: ComModel, f: F) -> Session<Req, Resp>
+where
+    F: FnOnce(SessionReceiver<Req>, SessionSender<Resp>) + Send + 'static,
+    Req: Send + 'static,
+    Resp: Send + 'static,
+{
+    ComRuntime::global().open_session(model, f)
+}
+
+/// Like [`open_session`], with a label identifying the handler in stats
+/// and watchdog reports.
+pub fn open_session_labeled<F, Req, Resp>(
+    model: ComModel,
+    label: &'static str,
+    f: F,
+) -> Session<Req, Resp>
+where
+    F: FnOnce(SessionReceiver<Req>, SessionSender<Resp>) + Send + 'static,
+    Req: Send + 'static,
+    Resp: Send + 'static,
+{
+    ComRuntime::global().open_session_labeled(model, label, f)
+}
+
+/// Like [`open_session`], returning an error instead of panicking when the
+/// handler cannot be queued.
+pub fn try_open_session<F, Req, Resp>(
+    model: ComModel,
+    f: F,
+) -> Result<Session<Req, Resp>, CallError>
+where
+    F: FnOnce(SessionReceiver<Req>, SessionSender<Resp>) + Send + 'static,
+    Req: Send + 'static,
+    Resp: Send + 'static,
+{
+    ComRuntime::global().try_open_session(model, f)
+}
+
+impl ComRuntime {
+    /// See [`open_session`].
+    pub fn open_session<F, Req, Resp>(&self, model: ComModel, f: F) -> Session<Req, Resp>
+    where
+        F: FnOnce(SessionReceiver<Req>, SessionSender<Resp>) + Send + 'static,
+        Req: Send + 'static,
+        Resp: Send + 'static,
+    {
+        self.open_session_labeled(model, std::any::type_name::<F>(), f)
+    }
+
+    /// See [`open_session_labeled`].
+    pub fn open_session_labeled<F, Req, Resp>(
+        &self,
+        model: ComModel,
+        label: &'static str,
+        f: F,
+    ) -> Session<Req, Resp>
+    where
+        F: FnOnce(SessionReceiver<Req>, SessionSender<Resp>) + Send + 'static,
+        Req: Send + 'static,
+        Resp: Send + 'static,
+    {
+        start_session(self, model, label, f).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// See [`try_open_session`].
+    pub fn try_open_session<F, Req, Resp>(
+        &self,
+        model: ComModel,
+        f: F,
+    ) -> Result<Session<Req, Resp>, CallError>
+    where
+        F: FnOnce(SessionReceiver<Req>, SessionSender<Resp>) + Send + 'static,
+        Req: Send + 'static,
+        Resp: Send + 'static,
+    {
+        start_session(self, model, std::any::type_name::<F>(), f)
+    }
+}
+
+fn start_session<F, Req, Resp>(
+    rt: &ComRuntime,
+    model: ComModel,
+    label: &'static str,
+    f: F,
+) -> Result<Session<Req, Resp>, CallError>
+where
+    F: FnOnce(SessionReceiver<Req>, SessionSender<Resp>) + Send + 'static,
+    Req: Send + 'static,
+    Resp: Send + 'static,
+{
+    let (req_tx, req_rx) = mpsc::channel();
+    let (resp_tx, resp_rx) = mpsc::channel();
+    let handler = move || {
+        f(
+            SessionReceiver { rx: req_rx },
+            SessionSender { tx: resp_tx },
+        )
+    };
+
+    let (done_tx, done_rx) = mpsc::channel::<Reply>();
+    let task = Box::new(Unwatched(Box::new(TaskImpl {
+        f: Some(handler),
+        label,
+    })));
+    let apartment = rt.submit_queued(model, Message::Sync(task, done_tx))?;
+    Ok(Session {
+        responses: resp_rx,
+        requests: Some(req_tx),
+        done: Some(done_rx),
+        apartment,
+    })
+}
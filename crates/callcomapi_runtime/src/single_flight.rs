@@ -0,0 +1,257 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::panic::{AssertUnwindSafe, resume_unwind};
+use std::sync::{Arc, Mutex};
+
+use futures::FutureExt;
+use futures::channel::oneshot;
+use futures::future::{BoxFuture, Shared, WeakShared};
+
+use crate::detached::panic_message;
+use crate::keyed::__key_hash;
+use crate::{CallError, ComModel, ComRuntime, dispatch_async};
+
+/// In-flight coalesced calls of a runtime, bucketed by label and key hash.
+#[derive(Clone, Default)]
+pub(crate) struct Flights(Arc<Mutex<FlightMap>>);
+
+#[derive(Default)]
+struct FlightMap {
+    next_id: u64,
+    entries: HashMap<(&'static str, u64), Vec<Box<dyn Waiting>>>,
+}
+
+/// A type-erased [`Entry`].
+trait Waiting: Send {
+    fn as_any(&self) -> &dyn Any;
+    fn id(&self) -> u64;
+    /// Whether any waiter still holds the flight.
+    fn is_live(&self) -> bool;
+}
+
+/// A flight and the key it was started for. Only a [`WeakShared`] is kept,
+/// so a flight whose waiters were all dropped is not joined again.
+struct Entry<K, R> {
+    id: u64,
+    key: K,
+    flight: WeakShared<BoxFuture<'static, Outcome<R>>>,
+}
+
+impl<K, R> Waiting for Entry<K, R>
+where
+    K: Send + 'static,
+    R: Clone + Send + Sync + 'static,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn is_live(&self) -> bool {
+        self.flight.upgrade().is_some()
+    }
+}
+
+/// How a flight ended, shared by every waiter.
+#[derive(Clone)]
+enum Outcome<R> {
+    Done(R),
+    Failed(CallError),
+    Panicked(Arc<str>),
+}
+
+type Flight<R> = Shared<BoxFuture<'static, Outcome<R>>>;
+
+impl<R> Outcome<R> {
+    /// Hand the result to one waiter, re-raising the task's panic.
+    fn into_result(self) -> R {
+        match self {
+            Outcome::Done(r) => r,
+            Outcome::Failed(e) => panic!("{e}"),
+            Outcome::Panicked(message) => resume_unwind(Box::new(message.to_string())),
+        }
+    }
+}
+
+/// Run `f` on the apartment for `model`, sharing one execution among all
+/// concurrent calls from the same call site with an equal `key`.
+///
+/// A call made while an identical one is in flight waits for it and gets a
+/// clone of its result; a call made afterwards runs again. If the task
+/// panics, every waiter panics with its message.
+pub fn call_single_flight<K, F, R>(model: ComModel, key: K, f: F) -> R
+where
+    K: Eq + Hash + Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+    R: Clone + Send + Sync + 'static,
+{
+    ComRuntime::global().call_single_flight(model, key, f)
+}
+
+/// Like [`call_single_flight`], coalescing calls with the same `label`
+/// rather than the same call site.
+pub fn call_single_flight_labeled<K, F, R>(model: ComModel, key: K, label: &'static str, f: F) -> R
+where
+    K: Eq + Hash + Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+    R: Clone + Send + Sync + 'static,
+{
+    ComRuntime::global().call_single_flight_labeled(model, key, label, f)
+}
+
+/// Async version of [`call_single_flight`].
+pub fn call_single_flight_async<K, F, R>(
+    model: ComModel,
+    key: K,
+    f: F,
+) -> impl Future<Output = R> + use<K, F, R>
+where
+    K: Eq + Hash + Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+    R: Clone + Send + Sync + 'static,
+{
+    ComRuntime::global().call_single_flight_async(model, key, f)
+}
+
+/// Async version of [`call_single_flight_labeled`].
+pub fn call_single_flight_async_labeled<K, F, R>(
+    model: ComModel,
+    key: K,
+    label: &'static str,
+    f: F,
+) -> impl Future<Output = R> + use<K, F, R>
+where
+    K: Eq + Hash + Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+    R: Clone + Send + Sync + 'static,
+{
+    ComRuntime::global().call_single_flight_async_labeled(model, key, label, f)
+}
+
+impl ComRuntime {
+    /// See [`call_single_flight`].
+    pub fn call_single_flight<K, F, R>(&self, model: ComModel, key: K, f: F) -> R
+    where
+        K: Eq + Hash + Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+        R: Clone + Send + Sync + 'static,
+    {
+        self.call_single_flight_labeled(model, key, std::any::type_name::<F>(), f)
+    }
+
+    /// See [`call_single_flight_labeled`].
+    pub fn call_single_flight_labeled<K, F, R>(
+        &self,
+        model: ComModel,
+        key: K,
+        label: &'static str,
+        f: F,
+    ) -> R
+    where
+        K: Eq + Hash + Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+        R: Clone + Send + Sync + 'static,
+    {
+        futures::executor::block_on(join(self, model, key, label, f)).into_result()
+    }
+
+    /// See [`call_single_flight_async`].
+    pub fn call_single_flight_async<K, F, R>(
+        &self,
+        model: ComModel,
+        key: K,
+        f: F,
+    ) -> impl Future<Output = R> + use<K, F, R>
+    where
+        K: Eq + Hash + Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+        R: Clone + Send + Sync + 'static,
+    {
+        self.call_single_flight_async_labeled(model, key, std::any::type_name::<F>(), f)
+    }
+
+    /// See [`call_single_flight_async_labeled`].
+    pub fn call_single_flight_async_labeled<K, F, R>(
+        &self,
+        model: ComModel,
+        key: K,
+        label: &'static str,
+        f: F,
+    ) -> impl Future<Output = R> + use<K, F, R>
+    where
+        K: Eq + Hash + Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+        R: Clone + Send + Sync + 'static,
+    {
+        let flight = join(self, model, key, label, f);
+        async move { flight.await.into_result() }
+    }
+}
+
+/// Join the flight for `(label, key)`, starting it with `f` if there is none.
+fn join<K, F, R>(rt: &ComRuntime, model: ComModel, key: K, label: &'static str, f: F) -> Flight<R>
+where
+    K: Eq + Hash + Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+    R: Clone + Send + Sync + 'static,
+{
+    let flights = rt.shared.flights.clone();
+    let bucket = (label, __key_hash(&key));
+    let mut map = flights.0.lock().unwrap();
+    // a result of another type under the same label just runs alone
+    let running = map.entries.get(&bucket).and_then(|entries| {
+        entries
+            .iter()
+            .filter_map(|e| e.as_any().downcast_ref::<Entry<K, R>>())
+            .find(|e| e.key == key)
+            .and_then(|e| e.flight.upgrade())
+    });
+    if let Some(flight) = running {
+        return flight;
+    }
+
+    // flights whose waiters all went away never finish to remove themselves
+    map.entries.retain(|_, entries| {
+        entries.retain(|e| e.is_live());
+        !entries.is_empty()
+    });
+    let id = map.next_id;
+    map.next_id += 1;
+
+    let (task_tx, task_rx) = oneshot::channel::<BoxFuture<'static, Result<R, CallError>>>();
+    let registry = flights.clone();
+    let flight: Flight<R> = async move {
+        let task = task_rx.await.expect("the leader submits the task");
+        let outcome = match AssertUnwindSafe(task).catch_unwind().await {
+            Ok(Ok(r)) => Outcome::Done(r),
+            Ok(Err(e)) => Outcome::Failed(e),
+            Err(payload) => Outcome::Panicked(panic_message(&*payload).into()),
+        };
+        // later calls start a new flight
+        let mut map = registry.0.lock().unwrap();
+        if let Some(entries) = map.entries.get_mut(&bucket) {
+            entries.retain(|e| e.id() != id);
+            if entries.is_empty() {
+                map.entries.remove(&bucket);
+            }
+        }
+        outcome
+    }
+    .boxed()
+    .shared();
+    let entry = Entry {
+        id,
+        key,
+        flight: flight.downgrade().expect("not yet polled"),
+    };
+    map.entries.entry(bucket).or_default().push(Box::new(entry));
+
+    // submitted without the lock, since inline tasks run right away
+    drop(map);
+    let _ = task_tx.send(dispatch_async(rt, model, label, f).boxed());
+    flight
+}
@@ -0,0 +1,139 @@
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use crate::CircuitState;
+use crate::apartment::{Apartment, ApartmentState};
+use crate::{ComModel, ComRuntime};
+
+/// The task an apartment worker is executing at the time of the snapshot.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct RunningTaskStats {
+    pub label: &'static str,
+    pub elapsed: Duration,
+}
+
+/// Point-in-time counters for a single apartment.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct ApartmentStats {
+    pub model: ComModel,
+    pub state: ApartmentState,
+    /// `false` unless running, or while the watchdog considers the current
+    /// worker hung.
+    pub healthy: bool,
+    pub queued: usize,
+    pub tasks_completed: u64,
+    pub hung_tasks: u64,
+    /// Workers abandoned by the watchdog in favour of a fresh one.
+    pub replacements: u64,
+    /// Dead workers replaced under the apartment's restart policy.
+    pub restarts: u64,
+    /// Healthy workers retired under the apartment's recycle policy.
+    pub recycles: u64,
+    pub running: Option<RunningTaskStats>,
+    /// The apartment's circuit breaker, if it has one.
+    pub circuit: Option<CircuitStats>,
+}
+
+/// Hit and miss counters of one [`ResultCache`](crate::ResultCache).
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct CacheStats {
+    pub label: &'static str,
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries currently stored, including expired ones not yet evicted.
+    pub entries: usize,
+}
+
+/// State and counters of a circuit breaker.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct CircuitStats {
+    pub state: CircuitState,
+    /// Failures in a row counting towards opening the breaker.
+    pub recent_failures: usize,
+    /// Times the breaker has opened.
+    pub opened: u64,
+    /// Calls failed without running while the breaker was open.
+    pub rejected: u64,
+}
+
+/// A function's [`CircuitBreaker`](crate::CircuitBreaker).
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct BreakerStats {
+    pub label: &'static str,
+    pub circuit: CircuitStats,
+}
+
+/// Point-in-time view of the runtime.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct RuntimeStats {
+    pub apartments: Vec<ApartmentStats>,
+    /// Result caches that have been used through the runtime.
+    pub caches: Vec<CacheStats>,
+    /// Function circuit breakers that have been used through the runtime.
+    pub breakers: Vec<BreakerStats>,
+}
+
+impl RuntimeStats {
+    pub fn apartment(&self, model: ComModel) -> Option<&ApartmentStats> {
+        self.apartments.iter().find(|a| a.model == model)
+    }
+
+    pub fn cache(&self, label: &str) -> Option<&CacheStats> {
+        self.caches.iter().find(|c| c.label == label)
+    }
+
+    pub fn breaker(&self, label: &str) -> Option<&BreakerStats> {
+        self.breakers.iter().find(|b| b.label == label)
+    }
+}
+
+/// Collect stats for every apartment of the global runtime that has been started.
+pub fn stats() -> RuntimeStats {
+    ComRuntime::global().stats()
+}
+
+impl ComRuntime {
+    /// Collect stats for every apartment of this runtime that has been started.
+    pub fn stats(&self) -> RuntimeStats {
+        RuntimeStats {
+            apartments: self
+                .shared
+                .apartments()
+                .iter()
+                .map(|a| apartment_stats(a))
+                .collect(),
+            caches: self.shared.caches.stats(),
+            breakers: self.shared.breakers.stats(),
+        }
+    }
+}
+
+fn apartment_stats(apartment: &Apartment) -> ApartmentStats {
+    let state = apartment.state();
+    let running = apartment.current_slot().running();
+    ApartmentStats {
+        model: apartment.model,
+        state,
+        healthy: state == ApartmentState::Running && !running.as_ref().is_some_and(|t| t.flagged),
+        queued: apartment.queued.load(Ordering::Relaxed),
+        tasks_completed: apartment.completed.load(Ordering::Relaxed),
+        hung_tasks: apartment.hung.load(Ordering::Relaxed),
+        replacements: apartment.replacements.load(Ordering::Relaxed),
+        restarts: apartment.restarts.load(Ordering::Relaxed),
+        recycles: apartment.recycles.load(Ordering::Relaxed),
+        running: running.map(|t| RunningTaskStats {
+            label: t.label,
+            elapsed: t.started.elapsed(),
+        }),
+        circuit: apartment
+            .circuit
+            .is_enabled()
+            .then(|| apartment.circuit.stats()),
+    }
+}
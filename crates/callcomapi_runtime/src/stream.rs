@@ -0,0 +1,156 @@
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use futures::channel::mpsc;
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::{SinkExt, Stream, StreamExt};
+
+use crate::{CallError, ComModel, ComRuntime, TaskImpl, Unwatched, dispatch_task_async};
+
+/// Items buffered between the worker and the caller before `send` blocks.
+const STREAM_BUFFER: usize = 16;
+
+/// The caller dropped the stream; the producer should stop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StreamClosed;
+
+impl fmt::Display for StreamClosed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("stream receiver was dropped")
+    }
+}
+
+impl std::error::Error for StreamClosed {}
+
+/// Worker-side handle used to push items to an [`ApartmentStream`].
+pub struct StreamSink<T> {
+    tx: SinkTx<T>,
+}
+
+enum SinkTx<T> {
+    Bounded(mpsc::Sender<T>),
+    /// Inline producers run before the caller polls, so they cannot wait for room.
+    Unbounded(mpsc::UnboundedSender<T>),
+}
+
+impl<T> StreamSink<T> {
+    /// Send an item to the caller, blocking the worker while the caller's
+    /// buffer is full. Fails once the stream has been dropped.
+    pub fn send(&mut self, item: T) -> Result<(), StreamClosed> {
+        match &mut self.tx {
+            SinkTx::Bounded(tx) => {
+                futures::executor::block_on(tx.send(item)).map_err(|_| StreamClosed)
+            }
+            SinkTx::Unbounded(tx) => tx.unbounded_send(item).map_err(|_| StreamClosed),
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        match &self.tx {
+            SinkTx::Bounded(tx) => tx.is_closed(),
+            SinkTx::Unbounded(tx) => tx.is_closed(),
+        }
+    }
+}
+
+/// Items produced by a [`call_stream`] task, in the order they were sent.
+///
+/// Ends when the producer returns. A panic in the producer is re-raised when
+/// the stream is polled past its last item. Dropping the stream makes further
+/// [`StreamSink::send`] calls fail.
+pub struct ApartmentStream<T> {
+    items: BoxStream<'static, T>,
+    done: Option<BoxFuture<'static, Result<(), CallError>>>,
+}
+
+impl<T> Stream for ApartmentStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let this = self.get_mut();
+        if let Some(item) = ready!(this.items.poll_next_unpin(cx)) {
+            return Poll::Ready(Some(item));
+        }
+
+        // the producer has dropped its sink; surface how it finished
+        if let Some(done) = this.done.as_mut() {
+            let res = ready!(done.as_mut().poll(cx));
+            this.done = None;
+            if let Err(e) = res {
+                panic!("{e}");
+            }
+        }
+        Poll::Ready(None)
+    }
+}
+
+/// Run `f` on the apartment for `model`, streaming the items it passes to
+/// its [`StreamSink`] back to the caller as they are produced.
+///
+/// The worker is occupied until `f` returns, including while it waits for
+/// the caller to make room in the buffer; the watchdog does not count that
+/// as a hang.
+pub fn call_stream<F, T>(model: ComModel, f: F) -> ApartmentStream<T>
+where
+    F: FnOnce(&mut StreamSink<T>) + Send + 'static,
+    T: Send + 'static,
+{
+    ComRuntime::global().call_stream(model, f)
+}
+
+/// Like [`call_stream`], with a label identifying the task in stats and
+/// watchdog reports.
+pub fn call_stream_labeled<F, T>(model: ComModel, label: &'static str, f: F) -> ApartmentStream<T>
+where
+    F: FnOnce(&mut StreamSink<T>) + Send + 'static,
+    T: Send + 'static,
+{
+    ComRuntime::global().call_stream_labeled(model, label, f)
+}
+
+impl ComRuntime {
+    /// See [`call_stream`].
+    pub fn call_stream<F, T>(&self, model: ComModel, f: F) -> ApartmentStream<T>
+    where
+        F: FnOnce(&mut StreamSink<T>) + Send + 'static,
+        T: Send + 'static,
+    {
+        self.call_stream_labeled(model, std::any::type_name::<F>(), f)
+    }
+
+    /// See [`call_stream_labeled`].
+    pub fn call_stream_labeled<F, T>(
+        &self,
+        model: ComModel,
+        label: &'static str,
+        f: F,
+    ) -> ApartmentStream<T>
+    where
+        F: FnOnce(&mut StreamSink<T>) + Send + 'static,
+        T: Send + 'static,
+    {
+        let (tx, rx) = if self.is_inline() {
+            let (tx, rx) = mpsc::unbounded();
+            (SinkTx::Unbounded(tx), rx.boxed())
+        } else {
+            let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+            (SinkTx::Bounded(tx), rx.boxed())
+        };
+        let producer = move || {
+            let mut sink = StreamSink { tx };
+            f(&mut sink);
+        };
+        let task = Box::new(Unwatched(Box::new(TaskImpl {
+            f: Some(producer),
+            label,
+        })));
+        let done = dispatch_task_async::<()>(self, model, task);
+
+        ApartmentStream {
+            items: rx,
+            done: Some(Box::pin(done)),
+        }
+    }
+}
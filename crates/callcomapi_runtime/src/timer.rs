@@ -0,0 +1,269 @@
+use std::cmp::{Ordering as CmpOrdering, Reverse};
+use std::collections::BinaryHeap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+use std::time::{Duration, Instant};
+
+use crate::{CallError, ComModel, ComRuntime, Message, Task};
+
+/// Delayed and periodic tasks of one apartment, fired by its worker loop.
+#[derive(Default)]
+pub(crate) struct Timers {
+    inner: Arc<Mutex<TimersInner>>,
+}
+
+#[derive(Default)]
+struct TimersInner {
+    heap: BinaryHeap<Reverse<Entry>>,
+    next_seq: u64,
+    closed: bool,
+}
+
+struct Entry {
+    due: Instant,
+    /// Keeps timers due at the same instant in scheduling order.
+    seq: u64,
+    timer: Arc<Timer>,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        (self.due, self.seq) == (other.due, other.seq)
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        (self.due, self.seq).cmp(&(other.due, other.seq))
+    }
+}
+
+struct Timer {
+    f: Mutex<Box<dyn FnMut() + Send>>,
+    interval: Option<Duration>,
+    label: &'static str,
+    cancelled: AtomicBool,
+}
+
+impl Timers {
+    /// Add `timer`, returning `true` if it is now the first one due.
+    /// Returns `None` once the apartment has been closed.
+    fn insert(&self, due: Instant, timer: Arc<Timer>) -> Option<bool> {
+        self.inner.lock().unwrap().insert(due, timer)
+    }
+
+    /// How long until the next timer is due, if any is scheduled.
+    pub(crate) fn until_next(&self) -> Option<Duration> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.discard_cancelled();
+        let Reverse(entry) = inner.heap.peek()?;
+        Some(entry.due.saturating_duration_since(Instant::now()))
+    }
+
+    /// Take the next timer that is due as a detached message. A repeating
+    /// timer is scheduled again once that run finishes.
+    pub(crate) fn pop_due(&self) -> Option<Message> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.discard_cancelled();
+        if inner.heap.peek()?.0.due > Instant::now() {
+            return None;
+        }
+        let Reverse(entry) = inner.heap.pop().expect("peeked above");
+        Some(Message::Detached(Box::new(TimerTask {
+            timer: entry.timer,
+            timers: Arc::downgrade(&self.inner),
+        })))
+    }
+
+    /// Drop every timer and refuse new ones.
+    pub(crate) fn close(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.closed = true;
+        inner.heap.clear();
+    }
+}
+
+impl TimersInner {
+    fn insert(&mut self, due: Instant, timer: Arc<Timer>) -> Option<bool> {
+        if self.closed {
+            return None;
+        }
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let first = self.heap.peek().is_none_or(|Reverse(e)| due < e.due);
+        self.heap.push(Reverse(Entry { due, seq, timer }));
+        Some(first)
+    }
+
+    fn discard_cancelled(&mut self) {
+        while self
+            .heap
+            .peek()
+            .is_some_and(|Reverse(e)| e.timer.cancelled.load(Ordering::Acquire))
+        {
+            self.heap.pop();
+        }
+    }
+}
+
+struct TimerTask {
+    timer: Arc<Timer>,
+    timers: Weak<Mutex<TimersInner>>,
+}
+
+impl Task for TimerTask {
+    fn label(&self) -> &'static str {
+        self.timer.label
+    }
+
+    fn run(self: Box<Self>) -> Box<dyn std::any::Any + Send> {
+        if !self.timer.cancelled.load(Ordering::Acquire) {
+            // a panicking run does not stop later ones
+            let _next = Reschedule(&self);
+            let mut f = self.timer.f.lock().unwrap_or_else(PoisonError::into_inner);
+            f();
+        }
+        Box::new(())
+    }
+}
+
+/// Schedules the next run of a repeating timer when dropped, after its body
+/// has returned or panicked. Counting the interval from there, rather than
+/// from when the run was due, leaves the worker time for queued calls
+/// however long the body takes.
+struct Reschedule<'a>(&'a TimerTask);
+
+impl Drop for Reschedule<'_> {
+    fn drop(&mut self) {
+        let TimerTask { timer, timers } = self.0;
+        if let (Some(interval), Some(timers)) = (timer.interval, timers.upgrade()) {
+            let mut timers = timers.lock().unwrap_or_else(PoisonError::into_inner);
+            timers.insert(Instant::now() + interval, timer.clone());
+        }
+    }
+}
+
+/// A task scheduled with [`schedule_after`] or [`schedule_every`].
+///
+/// Dropping the handle leaves the task scheduled.
+pub struct ScheduledTask {
+    timer: Arc<Timer>,
+}
+
+impl ScheduledTask {
+    /// Stop the task from running again. A run already in progress finishes.
+    pub fn cancel(&self) {
+        self.timer.cancelled.store(true, Ordering::Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.timer.cancelled.load(Ordering::Acquire)
+    }
+}
+
+/// Run `f` once on the apartment for `model` after `delay`.
+///
+/// Timers are fired by the apartment's worker between tasks, so a run is
+/// late while a long task is executing. Panics go to the
+/// [error sink](crate::set_error_sink), like detached tasks. On a
+/// [hosted](crate::host_apartment) apartment, due timers fire the next time
+/// the host runs its pending tasks.
+pub fn schedule_after<F>(model: ComModel, delay: Duration, f: F) -> Result<ScheduledTask, CallError>
+where
+    F: FnOnce() + Send + 'static,
+{
+    ComRuntime::global().schedule_after(model, delay, f)
+}
+
+/// Run `f` on the apartment for `model` every `interval`, starting one
+/// `interval` from now, until cancelled or the runtime shuts down.
+///
+/// Each run is due one `interval` after the previous one finished, so a body
+/// slower than its interval does not keep the worker from queued calls. A
+/// panicking run is reported like in [`schedule_after`] and does not stop
+/// later runs.
+pub fn schedule_every<F>(
+    model: ComModel,
+    interval: Duration,
+    f: F,
+) -> Result<ScheduledTask, CallError>
+where
+    F: FnMut() + Send + 'static,
+{
+    ComRuntime::global().schedule_every(model, interval, f)
+}
+
+impl ComRuntime {
+    /// See [`schedule_after`].
+    pub fn schedule_after<F>(
+        &self,
+        model: ComModel,
+        delay: Duration,
+        f: F,
+    ) -> Result<ScheduledTask, CallError>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let mut f = Some(f);
+        let once = move || {
+            if let Some(f) = f.take() {
+                f()
+            }
+        };
+        schedule(self, model, delay, None, std::any::type_name::<F>(), once)
+    }
+
+    /// See [`schedule_every`].
+    pub fn schedule_every<F>(
+        &self,
+        model: ComModel,
+        interval: Duration,
+        f: F,
+    ) -> Result<ScheduledTask, CallError>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        assert!(
+            !interval.is_zero(),
+            "schedule_every needs a non-zero interval"
+        );
+        let label = std::any::type_name::<F>();
+        schedule(self, model, interval, Some(interval), label, f)
+    }
+}
+
+pub(crate) fn schedule(
+    rt: &ComRuntime,
+    model: ComModel,
+    delay: Duration,
+    interval: Option<Duration>,
+    label: &'static str,
+    f: impl FnMut() + Send + 'static,
+) -> Result<ScheduledTask, CallError> {
+    let apartment = rt.apartment(model)?;
+    let timer = Arc::new(Timer {
+        f: Mutex::new(Box::new(f)),
+        interval,
+        label,
+        cancelled: AtomicBool::new(false),
+    });
+    let first = apartment
+        .timers
+        .insert(Instant::now() + delay, timer.clone())
+        .ok_or(CallError::ShutDown(model))?;
+    // a worker waiting on a later deadline, or not started yet, needs a nudge
+    let handle = ScheduledTask { timer };
+    if first && let Err(error) = apartment.send(Message::Wake) {
+        handle.cancel();
+        return Err(error);
+    }
+    Ok(handle)
+}
@@ -0,0 +1,89 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::rc::Rc;
+
+use tokio::runtime::{Builder, Handle, Runtime};
+
+use crate::Task;
+
+/// Which tokio runtime drives async task bodies on an apartment worker.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TokioMode {
+    /// Each worker owns a current-thread runtime with all drivers enabled.
+    #[default]
+    CurrentThread,
+    /// Tasks enter the runtime handle of the thread that submitted them.
+    /// Tasks submitted outside a tokio runtime fall back to a plain executor.
+    CallerHandle,
+}
+
+thread_local! {
+    static WORKER_RUNTIME: RefCell<Option<Rc<Runtime>>> = const { RefCell::new(None) };
+}
+
+/// Keeps the worker's own runtime installed for the lifetime of the worker.
+pub(crate) struct WorkerRuntime(Option<Rc<Runtime>>);
+
+impl WorkerRuntime {
+    pub(crate) fn install(mode: TokioMode) -> Self {
+        if mode != TokioMode::CurrentThread {
+            return WorkerRuntime(None);
+        }
+        let rt = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("failed to build worker tokio runtime");
+        let rt = Rc::new(rt);
+        WORKER_RUNTIME.with_borrow_mut(|slot| *slot = Some(rt.clone()));
+        WorkerRuntime(Some(rt))
+    }
+
+    /// Enter the runtime context so sync task bodies can use tokio APIs too.
+    pub(crate) fn enter(&self) -> Option<tokio::runtime::EnterGuard<'_>> {
+        self.0.as_ref().map(|rt| rt.enter())
+    }
+}
+
+impl Drop for WorkerRuntime {
+    fn drop(&mut self) {
+        if self.0.is_some() {
+            WORKER_RUNTIME.with_borrow_mut(|slot| *slot = None);
+        }
+    }
+}
+
+/// Drive `future` on the worker's runtime, or the entered caller handle.
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    if let Some(rt) = WORKER_RUNTIME.with_borrow(|rt| rt.clone()) {
+        return rt.block_on(future);
+    }
+    match Handle::try_current() {
+        // an inline task may run on a thread that is already driving the
+        // runtime, where blocking on it again would panic
+        Ok(handle) if !crate::inline::running() => handle.block_on(future),
+        _ if crate::inline::running() => crate::inline::block_on(future),
+        _ => futures::executor::block_on(future),
+    }
+}
+
+/// Wraps a task so it runs inside the submitting thread's runtime context.
+pub(crate) struct CallerHandleTask {
+    pub(crate) task: Box<dyn Task>,
+    pub(crate) handle: Handle,
+}
+
+impl Task for CallerHandleTask {
+    fn label(&self) -> &'static str {
+        self.task.label()
+    }
+
+    fn run(self: Box<Self>) -> Box<dyn std::any::Any + Send> {
+        let this = *self;
+        let _enter = this.handle.enter();
+        this.task.run()
+    }
+
+    fn watched(&self) -> bool {
+        self.task.watched()
+    }
+}
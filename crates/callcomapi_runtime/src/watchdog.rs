@@ -0,0 +1,140 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::events::{RuntimeEvent, emit};
+use crate::runtime::Shared;
+use crate::{ComModel, ComRuntime};
+
+/// What the watchdog does after reporting a hung task.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HungTaskPolicy {
+    /// Only report; new work keeps queueing behind the hung task.
+    #[default]
+    Report,
+    /// Start a fresh worker for the apartment and route queued and new work
+    /// to it. The hung worker exits once its task returns.
+    Replace,
+}
+
+/// A task that has been running longer than the watchdog threshold.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct HungTask {
+    pub label: &'static str,
+    pub model: ComModel,
+    pub elapsed: Duration,
+}
+
+type HungCallback = Arc<dyn Fn(&HungTask) + Send + Sync>;
+
+/// Watchdog settings, see [`enable_watchdog`].
+#[derive(Clone)]
+pub struct WatchdogConfig {
+    threshold: Duration,
+    poll_interval: Duration,
+    policy: HungTaskPolicy,
+    on_hung: Option<HungCallback>,
+}
+
+impl WatchdogConfig {
+    /// Flag tasks running longer than `threshold`. Session handlers and
+    /// streaming calls run for as long as their caller keeps them open and
+    /// are never flagged.
+    pub fn new(threshold: Duration) -> Self {
+        WatchdogConfig {
+            threshold,
+            poll_interval: (threshold / 4).max(Duration::from_millis(1)),
+            policy: HungTaskPolicy::Report,
+            on_hung: None,
+        }
+    }
+
+    /// How often apartments are inspected. Defaults to a quarter of the threshold.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    pub fn policy(mut self, policy: HungTaskPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Called on the watchdog thread, once per hung task.
+    pub fn on_hung<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&HungTask) + Send + Sync + 'static,
+    {
+        self.on_hung = Some(Arc::new(f));
+        self
+    }
+}
+
+/// Start the watchdog thread for the global runtime, replacing any
+/// watchdog already running on it.
+pub fn enable_watchdog(config: WatchdogConfig) {
+    ComRuntime::global().enable_watchdog(config);
+}
+
+/// Stop the global runtime's watchdog thread, if one is running.
+pub fn disable_watchdog() {
+    ComRuntime::global().disable_watchdog();
+}
+
+impl ComRuntime {
+    /// Start a watchdog thread inspecting this runtime's apartments,
+    /// replacing any watchdog already running on it.
+    pub fn enable_watchdog(&self, config: WatchdogConfig) {
+        let stop = Arc::new(AtomicBool::new(false));
+        if let Some(previous) = self.shared.watchdog.lock().unwrap().replace(stop.clone()) {
+            previous.store(true, Ordering::Relaxed);
+        }
+
+        let shared = Arc::downgrade(&self.shared);
+        std::thread::spawn(move || {
+            loop {
+                std::thread::sleep(config.poll_interval);
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                let Some(shared) = shared.upgrade() else {
+                    break;
+                };
+                inspect(&config, &shared);
+            }
+        });
+    }
+
+    /// Stop this runtime's watchdog thread, if one is running.
+    pub fn disable_watchdog(&self) {
+        if let Some(stop) = self.shared.watchdog.lock().unwrap().take() {
+            stop.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+fn inspect(config: &WatchdogConfig, shared: &Shared) {
+    for apartment in shared.apartments() {
+        for (lane, slot) in apartment.slots().into_iter().enumerate() {
+            let Some(task) = slot.flag_if_older_than(config.threshold) else {
+                continue;
+            };
+            apartment.hung.fetch_add(1, Ordering::Relaxed);
+            apartment.circuit.record(false);
+
+            let hung = HungTask {
+                label: task.label,
+                model: apartment.model,
+                elapsed: task.started.elapsed(),
+            };
+            if let Some(on_hung) = &config.on_hung {
+                on_hung(&hung);
+            }
+            emit(RuntimeEvent::TaskHung(hung));
+            if config.policy == HungTaskPolicy::Replace {
+                apartment.replace_worker(lane);
+            }
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use callcomapi_runtime::{ComModel, call_batch, call_batch_async, stats};
+use std::thread;
+
+#[test]
+fn test_batch_runs_items_in_order_on_one_worker() {
+    let tasks: Vec<_> = (0..5)
+        .map(|i| {
+            move || {
+                if i == 2 {
+                    panic!("item {i} failed");
+                }
+                (i, thread::current().id())
+            }
+        })
+        .collect();
+    let results = call_batch(ComModel::STA, tasks);
+
+    assert_eq!(results.len(), 5);
+    assert!(results[2].is_err());
+    let ok: Vec<_> = results.into_iter().filter_map(Result::ok).collect();
+    assert_eq!(ok.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [0, 1, 3, 4]);
+    assert!(ok.iter().all(|(_, tid)| *tid == ok[0].1));
+
+    // the whole batch is a single task, and the panic did not kill the worker
+    let sta = stats().apartment(ComModel::STA).cloned().unwrap();
+    assert_eq!(sta.restarts, 0);
+}
+
+#[test]
+fn test_batch_async() {
+    let tasks: Vec<_> = (0..100).map(|i| move || i + 1).collect();
+    let results = futures::executor::block_on(call_batch_async(ComModel::MTA, tasks));
+    let values: Vec<i32> = results.into_iter().map(Result::unwrap).collect();
+    assert_eq!(values, (1..=100).collect::<Vec<_>>());
+}
@@ -0,0 +1,105 @@
+use callcomapi_runtime::{ComModel, ComRuntime, ResultCache, call_cached_async};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+static INVENTORY: ResultCache<&str, String> =
+    ResultCache::new("inventory", Duration::from_millis(100), 2);
+
+fn inventory(rt: &ComRuntime, host: &'static str) -> String {
+    rt.call_cached(ComModel::MTA, &INVENTORY, host, move || {
+        RUNS.fetch_add(1, Ordering::SeqCst);
+        format!("cpus of {host}")
+    })
+}
+
+#[test]
+fn test_cached_results_expire_and_invalidate() {
+    let rt = ComRuntime::new();
+    assert_eq!(inventory(&rt, "a"), "cpus of a");
+    assert_eq!(inventory(&rt, "a"), "cpus of a");
+    assert_eq!(RUNS.load(Ordering::SeqCst), 1);
+
+    INVENTORY.invalidate(&"a");
+    inventory(&rt, "a");
+    assert_eq!(RUNS.load(Ordering::SeqCst), 2);
+
+    // capacity 2: a third host evicts the oldest
+    inventory(&rt, "b");
+    inventory(&rt, "c");
+    inventory(&rt, "c");
+    assert_eq!(RUNS.load(Ordering::SeqCst), 4);
+    inventory(&rt, "a");
+    assert_eq!(RUNS.load(Ordering::SeqCst), 5);
+
+    thread::sleep(Duration::from_millis(150));
+    inventory(&rt, "a");
+    assert_eq!(RUNS.load(Ordering::SeqCst), 6);
+
+    let stats = rt.stats();
+    let cache = stats.cache("inventory").unwrap();
+    assert_eq!((cache.hits, cache.misses), (2, 6));
+    assert!(cache.entries <= 2);
+
+    INVENTORY.invalidate_all();
+    assert_eq!(INVENTORY.stats().entries, 0);
+}
+
+#[test]
+fn test_keys_with_equal_hashes_are_kept_apart() {
+    /// A key whose values all hash alike.
+    #[derive(Clone, PartialEq, Eq)]
+    struct Colliding(&'static str);
+
+    impl Hash for Colliding {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            state.write_u8(0);
+        }
+    }
+
+    static DRIVES: ResultCache<Colliding, &str> =
+        ResultCache::new("drives", Duration::from_secs(60), 8);
+    let rt = ComRuntime::new();
+    assert_eq!(
+        rt.call_cached(ComModel::MTA, &DRIVES, Colliding("c"), || "C:"),
+        "C:"
+    );
+    assert_eq!(
+        rt.call_cached(ComModel::MTA, &DRIVES, Colliding("d"), || "D:"),
+        "D:"
+    );
+    DRIVES.invalidate(&Colliding("d"));
+    assert_eq!(
+        rt.call_cached(ComModel::MTA, &DRIVES, Colliding("c"), || "?"),
+        "C:"
+    );
+}
+
+#[test]
+fn test_panics_are_not_cached() {
+    static FLAKY: ResultCache<u32, u32> = ResultCache::new("flaky", Duration::from_secs(60), 8);
+    let rt = ComRuntime::new();
+    let failed = std::panic::catch_unwind(|| {
+        rt.call_cached(ComModel::STA, &FLAKY, 1, || -> u32 {
+            panic!("rpc unavailable")
+        })
+    });
+    assert!(failed.is_err());
+    assert_eq!(rt.call_cached(ComModel::STA, &FLAKY, 1, || 7), 7);
+    assert_eq!(rt.call_cached(ComModel::STA, &FLAKY, 1, || 8), 7);
+}
+
+#[test]
+fn test_cached_async() {
+    static VOLUMES: ResultCache<(), Vec<char>> =
+        ResultCache::new("volumes", Duration::from_secs(60), 8);
+    let first = futures::executor::block_on(call_cached_async(ComModel::STA, &VOLUMES, (), || {
+        vec!['C', 'D']
+    }));
+    let second =
+        futures::executor::block_on(call_cached_async(ComModel::STA, &VOLUMES, (), || vec!['E']));
+    assert_eq!(first, second);
+    assert_eq!(VOLUMES.stats().hits, 1);
+}
@@ -0,0 +1,123 @@
+use callcomapi_runtime::{
+    ApartmentConfig, CallError, ComModel, ComRuntime, RestartPolicy, call_with_callback,
+    current_apartment,
+};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+#[test]
+fn test_callback_receives_result_off_the_worker() {
+    let (tx, rx) = mpsc::channel();
+    call_with_callback(
+        ComModel::STA,
+        || (current_apartment(), thread::current().id()),
+        move |res| {
+            tx.send((res, current_apartment(), thread::current().id()))
+                .unwrap();
+        },
+    );
+    let (res, callback_apartment, callback_thread) = rx.recv().unwrap();
+    let (task_apartment, task_thread) = res.unwrap();
+
+    assert_eq!(task_apartment, Some(ComModel::STA));
+    assert_eq!(callback_apartment, None);
+    assert_ne!(callback_thread, task_thread);
+    assert_ne!(callback_thread, thread::current().id());
+}
+
+#[test]
+fn test_callback_reports_panics_and_errors() {
+    let rt = ComRuntime::new();
+    let (tx, rx) = mpsc::channel();
+
+    let panicked = tx.clone();
+    rt.call_with_callback(
+        ComModel::MTA,
+        || -> u32 { panic!("query failed") },
+        move |res| panicked.send(res).unwrap(),
+    );
+    assert_eq!(rx.recv().unwrap(), Err(CallError::Panicked(ComModel::MTA)));
+
+    rt.shutdown();
+    rt.call_with_callback(ComModel::MTA, || 1, move |res| tx.send(res).unwrap());
+    assert_eq!(rx.recv().unwrap(), Err(CallError::ShutDown(ComModel::MTA)));
+}
+
+#[test]
+fn test_callbacks_are_posted_to_the_executor() {
+    // stands in for a GUI loop draining its queue on each tick
+    let queue: Arc<Mutex<Vec<Job>>> = Arc::default();
+    let results = Arc::new(Mutex::new(Vec::new()));
+
+    let rt = ComRuntime::new();
+    for i in 0..3 {
+        let queue = queue.clone();
+        let results = results.clone();
+        rt.call_with_executor(
+            ComModel::STA,
+            move || i * 10,
+            move |job| queue.lock().unwrap().push(job),
+            move |res| results.lock().unwrap().push(res.unwrap()),
+        );
+    }
+    rt.call_sync(ComModel::STA, || ());
+
+    assert!(results.lock().unwrap().is_empty());
+    let jobs: Vec<_> = queue.lock().unwrap().drain(..).collect();
+    assert_eq!(jobs.len(), 3);
+    jobs.into_iter().for_each(|job| job());
+    assert_eq!(*results.lock().unwrap(), [0, 10, 20]);
+}
+
+#[test]
+fn test_pending_call_can_be_polled() {
+    let rt = ComRuntime::new();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let mut pending = rt.start_call(ComModel::STA, move || {
+        release_rx.recv().unwrap();
+        current_apartment()
+    });
+
+    assert!(!pending.is_finished());
+    assert!(pending.try_take().is_none());
+    release_tx.send(()).unwrap();
+    while !pending.is_finished() {
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(pending.try_take(), Some(Ok(Some(ComModel::STA))));
+    assert!(pending.try_take().is_none());
+
+    assert_eq!(rt.start_call(ComModel::MTA, || 3).wait(), Ok(3));
+    rt.shutdown();
+    assert_eq!(
+        rt.start_call(ComModel::MTA, || 3).wait(),
+        Err(CallError::ShutDown(ComModel::MTA))
+    );
+}
+
+#[test]
+fn test_calls_queued_on_a_failing_apartment_report_it() {
+    let rt = ComRuntime::new();
+    rt.configure_apartment(
+        ComModel::MTA,
+        ApartmentConfig::default().restart_policy(RestartPolicy::never()),
+    );
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let crash = rt.start_call(ComModel::MTA, move || {
+        release_rx.recv().unwrap();
+        panic!("worker crashed")
+    });
+    let pending = rt.start_call(ComModel::MTA, || 1);
+    let (tx, rx) = mpsc::channel();
+    rt.call_with_callback(ComModel::MTA, || 2, move |res| tx.send(res).unwrap());
+
+    release_tx.send(()).unwrap();
+    drop(crash);
+    let failed = Err(CallError::ApartmentFailed(ComModel::MTA));
+    assert_eq!(pending.wait(), failed);
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(failed));
+}
@@ -0,0 +1,200 @@
+use callcomapi_runtime::{
+    ApartmentConfig, CallError, CircuitBreaker, CircuitOpen, CircuitPolicy, CircuitState, ComModel,
+    ComRuntime, call_with_breaker_async,
+};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use windows::core::{Error, HRESULT};
+
+const E_FAIL: HRESULT = HRESULT(0x8000_4005_u32 as i32);
+
+fn failing() -> windows::core::Result<u32> {
+    Err(Error::from(E_FAIL))
+}
+
+#[test]
+fn test_breaker_opens_and_recovers_after_cooldown() {
+    static DEVICES: CircuitBreaker = CircuitBreaker::new(
+        "devices",
+        CircuitPolicy::new(2, Duration::from_secs(10), Duration::from_millis(100)),
+    );
+    let rt = ComRuntime::new();
+    assert_eq!(
+        rt.call_with_breaker(ComModel::MTA, &DEVICES, failing),
+        Err(E_FAIL.into())
+    );
+    // a success in between resets the count
+    assert_eq!(
+        rt.call_with_breaker(ComModel::MTA, &DEVICES, || Ok(1)),
+        Ok(1)
+    );
+    rt.call_with_breaker(ComModel::MTA, &DEVICES, failing)
+        .unwrap_err();
+    assert_eq!(DEVICES.state(), CircuitState::Closed);
+    rt.call_with_breaker(ComModel::MTA, &DEVICES, failing)
+        .unwrap_err();
+    assert_eq!(DEVICES.state(), CircuitState::Open);
+
+    let error = rt
+        .call_with_breaker(ComModel::MTA, &DEVICES, || -> windows::core::Result<u32> {
+            unreachable!()
+        })
+        .unwrap_err();
+    assert_eq!(error.code(), CircuitOpen::CODE);
+
+    // the failed trial opens it again
+    thread::sleep(Duration::from_millis(150));
+    assert_eq!(DEVICES.state(), CircuitState::HalfOpen);
+    rt.call_with_breaker(ComModel::MTA, &DEVICES, failing)
+        .unwrap_err();
+    assert_eq!(DEVICES.state(), CircuitState::Open);
+
+    thread::sleep(Duration::from_millis(150));
+    assert_eq!(
+        rt.call_with_breaker(ComModel::MTA, &DEVICES, || Ok(2)),
+        Ok(2)
+    );
+    assert_eq!(DEVICES.state(), CircuitState::Closed);
+
+    let stats = rt.stats();
+    let breaker = stats.breaker("devices").unwrap();
+    assert_eq!((breaker.circuit.opened, breaker.circuit.rejected), (2, 1));
+    assert_eq!(breaker.circuit.recent_failures, 0);
+}
+
+#[test]
+fn test_half_open_breaker_admits_one_trial_at_a_time() {
+    static SCANNERS: CircuitBreaker = CircuitBreaker::new(
+        "scanners",
+        CircuitPolicy::new(1, Duration::from_secs(10), Duration::from_millis(50)),
+    );
+    let rt = ComRuntime::new();
+    rt.call_with_breaker(ComModel::MTA, &SCANNERS, failing)
+        .unwrap_err();
+    thread::sleep(Duration::from_millis(80));
+
+    let (started_tx, started_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    thread::scope(|s| {
+        let trial = s.spawn(|| {
+            rt.call_with_breaker(ComModel::MTA, &SCANNERS, move || {
+                started_tx.send(()).unwrap();
+                release_rx.recv().unwrap();
+                Ok(1)
+            })
+        });
+        started_rx.recv().unwrap();
+
+        // the others are turned away while the trial runs
+        let error = rt
+            .call_with_breaker(
+                ComModel::STA,
+                &SCANNERS,
+                || -> windows::core::Result<u32> { unreachable!() },
+            )
+            .unwrap_err();
+        assert_eq!(error.code(), CircuitOpen::CODE);
+        assert_eq!(SCANNERS.state(), CircuitState::HalfOpen);
+
+        release_tx.send(()).unwrap();
+        assert_eq!(trial.join().unwrap(), Ok(1));
+    });
+    assert_eq!(SCANNERS.state(), CircuitState::Closed);
+    assert_eq!(
+        rt.call_with_breaker(ComModel::STA, &SCANNERS, || Ok(2)),
+        Ok(2)
+    );
+    assert_eq!(SCANNERS.stats().rejected, 1);
+}
+
+#[test]
+fn test_only_failures_within_the_window_count() {
+    static PRINTERS: CircuitBreaker = CircuitBreaker::new(
+        "printers",
+        CircuitPolicy::new(2, Duration::from_millis(50), Duration::from_secs(60)),
+    );
+    let rt = ComRuntime::new();
+    rt.call_with_breaker(ComModel::STA, &PRINTERS, failing)
+        .unwrap_err();
+    thread::sleep(Duration::from_millis(100));
+    rt.call_with_breaker(ComModel::STA, &PRINTERS, failing)
+        .unwrap_err();
+    assert_eq!(PRINTERS.state(), CircuitState::Closed);
+
+    // panics count as failures and are re-raised
+    let panicked = catch_unwind(|| {
+        rt.call_with_breaker(ComModel::STA, &PRINTERS, || -> windows::core::Result<()> {
+            panic!("printer spooler crashed")
+        })
+    });
+    assert!(panicked.is_err());
+    assert_eq!(PRINTERS.state(), CircuitState::Open);
+
+    PRINTERS.reset();
+    assert_eq!(
+        rt.call_with_breaker(ComModel::STA, &PRINTERS, || Ok(())),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_apartment_breaker() {
+    let rt = ComRuntime::new();
+    rt.configure_apartment(
+        ComModel::STA,
+        ApartmentConfig::default().circuit_breaker(CircuitPolicy::new(
+            2,
+            Duration::from_secs(10),
+            Duration::from_millis(100),
+        )),
+    );
+    for _ in 0..2 {
+        let crash = catch_unwind(AssertUnwindSafe(|| {
+            rt.call_sync(ComModel::STA, || panic!("boom"))
+        }));
+        assert!(crash.is_err());
+    }
+    assert_eq!(
+        rt.try_call_sync(ComModel::STA, || 1),
+        Err(CallError::CircuitOpen(ComModel::STA))
+    );
+    let circuit = rt
+        .stats()
+        .apartment(ComModel::STA)
+        .unwrap()
+        .circuit
+        .clone()
+        .unwrap();
+    assert_eq!(circuit.state, CircuitState::Open);
+    assert_eq!((circuit.opened, circuit.rejected), (1, 1));
+
+    thread::sleep(Duration::from_millis(150));
+    assert_eq!(rt.try_call_sync(ComModel::STA, || 2), Ok(2));
+    assert_eq!(rt.try_call_sync(ComModel::STA, || 3), Ok(3));
+
+    // apartments without a policy report no breaker
+    rt.call_sync(ComModel::MTA, || ());
+    assert!(
+        rt.stats()
+            .apartment(ComModel::MTA)
+            .unwrap()
+            .circuit
+            .is_none()
+    );
+}
+
+#[test]
+fn test_breaker_async() {
+    static SENSORS: CircuitBreaker = CircuitBreaker::new(
+        "sensors",
+        CircuitPolicy::new(1, Duration::from_secs(10), Duration::from_secs(60)),
+    );
+    let first =
+        futures::executor::block_on(call_with_breaker_async(ComModel::MTA, &SENSORS, failing));
+    assert_eq!(first, Err(E_FAIL.into()));
+    let second =
+        futures::executor::block_on(call_with_breaker_async(ComModel::MTA, &SENSORS, || Ok(1)));
+    assert_eq!(second.unwrap_err().code(), CircuitOpen::CODE);
+}
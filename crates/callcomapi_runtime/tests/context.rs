@@ -0,0 +1,97 @@
+use callcomapi_runtime::{ComModel, ComRuntime};
+use std::cell::{Cell, RefCell};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::{Arc, mpsc};
+use std::thread;
+use std::time::Duration;
+
+thread_local! {
+    static REQUEST_ID: Cell<Option<u64>> = const { Cell::new(None) };
+    static TENANT: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+fn runtime() -> ComRuntime {
+    let rt = ComRuntime::new();
+    rt.add_context_hook(|| REQUEST_ID.get(), |id| REQUEST_ID.replace(id));
+    rt.add_context_hook(
+        || TENANT.with_borrow(Clone::clone),
+        |tenant| TENANT.replace(tenant),
+    );
+    rt
+}
+
+#[test]
+fn test_context_reaches_the_worker_and_is_cleared() {
+    let rt = runtime();
+    REQUEST_ID.set(Some(7));
+    TENANT.set("contoso".to_string());
+
+    let seen = rt.call_sync(ComModel::STA, || {
+        (REQUEST_ID.get(), TENANT.with_borrow(Clone::clone))
+    });
+    assert_eq!(seen, (Some(7), "contoso".to_string()));
+
+    let seen = futures::executor::block_on(rt.call_async(ComModel::MTA, || REQUEST_ID.get()));
+    assert_eq!(seen, Some(7));
+
+    // a task submitted from a thread without context sees none on the worker
+    let seen = std::thread::scope(|s| {
+        s.spawn(|| rt.call_sync(ComModel::STA, || REQUEST_ID.get()))
+            .join()
+            .unwrap()
+    });
+    assert_eq!(seen, None);
+}
+
+#[test]
+fn test_context_is_cleared_after_a_panic() {
+    // a worker exits after a panic, but a hosted thread keeps serving
+    let rt = Arc::new(runtime());
+    let (wake_tx, wake_rx) = mpsc::channel();
+    let host = rt
+        .host_apartment(ComModel::STA, move || {
+            let _ = wake_tx.send(());
+        })
+        .unwrap();
+
+    let (seen_tx, seen_rx) = mpsc::channel();
+    let caller = {
+        let rt = rt.clone();
+        thread::spawn(move || {
+            REQUEST_ID.set(Some(7));
+            catch_unwind(AssertUnwindSafe(|| {
+                rt.call_sync(ComModel::STA, move || {
+                    seen_tx.send(REQUEST_ID.get()).unwrap();
+                    panic!("request failed")
+                })
+            }))
+            .is_err()
+        })
+    };
+    wake_rx.recv().unwrap();
+    host.run_pending();
+    assert!(caller.join().unwrap());
+    assert_eq!(seen_rx.recv().unwrap(), Some(7));
+
+    // timers run without context hooks, so this sees whatever was left
+    let (left_tx, left_rx) = mpsc::channel();
+    rt.schedule_after(ComModel::STA, Duration::ZERO, move || {
+        left_tx.send(REQUEST_ID.get()).unwrap();
+    })
+    .unwrap();
+    host.run_pending();
+    assert_eq!(left_rx.recv().unwrap(), None);
+}
+
+#[test]
+fn test_inline_tasks_keep_the_callers_context() {
+    let rt = runtime();
+    rt.set_inline(true);
+    REQUEST_ID.set(Some(1));
+    let seen = rt.call_sync(ComModel::STA, || {
+        REQUEST_ID.set(Some(2));
+        REQUEST_ID.get()
+    });
+    assert_eq!(seen, Some(2));
+    assert_eq!(REQUEST_ID.get(), Some(1));
+}
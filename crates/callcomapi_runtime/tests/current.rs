@@ -0,0 +1,58 @@
+use callcomapi_runtime::{
+    ComModel, ComRuntime, assert_apartment, call_sync, current_apartment, init_com,
+    is_runtime_worker,
+};
+use std::panic::catch_unwind;
+use std::thread;
+
+#[test]
+fn test_workers_report_their_apartment() {
+    let sta = call_sync(ComModel::STA, || (current_apartment(), is_runtime_worker()));
+    assert_eq!(sta, (Some(ComModel::STA), true));
+
+    let rt = ComRuntime::new();
+    let mta = rt.call_sync(ComModel::MTA, || {
+        assert_apartment!(MTA);
+        (current_apartment(), is_runtime_worker())
+    });
+    assert_eq!(mta, (Some(ComModel::MTA), true));
+}
+
+#[test]
+fn test_init_com_tracks_nested_initialization() {
+    thread::spawn(|| {
+        assert_eq!(current_apartment(), None);
+        assert!(!is_runtime_worker());
+        {
+            let _outer = unsafe { init_com(ComModel::STA) };
+            assert_eq!(current_apartment(), Some(ComModel::STA));
+            {
+                let _inner = unsafe { init_com(ComModel::STA) };
+                assert_apartment!(STA);
+            }
+            assert_eq!(current_apartment(), Some(ComModel::STA));
+        }
+        assert_eq!(current_apartment(), None);
+    })
+    .join()
+    .unwrap();
+}
+
+#[test]
+fn test_assert_apartment_failure_message() {
+    let err = thread::spawn(|| catch_unwind(|| assert_apartment!(STA)).unwrap_err())
+        .join()
+        .unwrap();
+    let message = err.downcast_ref::<String>().unwrap();
+    assert!(message.contains("expected the current thread to be in the STA apartment"));
+    assert!(message.contains("not initialized"));
+
+    let err = call_sync(ComModel::MTA, || {
+        catch_unwind(|| assert_apartment!(STA))
+            .unwrap_err()
+            .downcast_ref::<String>()
+            .cloned()
+            .unwrap()
+    });
+    assert!(err.ends_with("but it is in the MTA apartment"));
+}
@@ -0,0 +1,31 @@
+use callcomapi_runtime::{
+    ComModel, DetachedError, call_sync, set_error_sink, spawn_detached, spawn_detached_labeled,
+};
+use std::sync::mpsc;
+use std::thread;
+
+#[test]
+fn test_detached_tasks_report_panics_to_sink() {
+    let (err_tx, err_rx) = mpsc::channel();
+    let err_tx = std::sync::Mutex::new(err_tx);
+    set_error_sink(move |e| err_tx.lock().unwrap().send(e.clone()).unwrap());
+
+    let (tx, rx) = mpsc::channel();
+    spawn_detached(ComModel::STA, move || {
+        tx.send(thread::current().id()).unwrap()
+    });
+    let worker = rx.recv().unwrap();
+    assert_ne!(worker, thread::current().id());
+
+    spawn_detached_labeled(ComModel::STA, "poke_shell", || panic!("shell is gone"));
+    match err_rx.recv().unwrap() {
+        DetachedError::Panicked { label, message } => {
+            assert_eq!(label, "poke_shell");
+            assert_eq!(message, "shell is gone");
+        }
+        other => panic!("unexpected error: {other}"),
+    }
+
+    // the apartment keeps serving work afterwards
+    assert_eq!(call_sync(ComModel::STA, || 7), 7);
+}
@@ -0,0 +1,53 @@
+use callcomapi_runtime::{
+    ApartmentConfig, CallError, ComModel, ComRuntime, call_sync, call_sync_ephemeral,
+    current_apartment,
+};
+use std::panic::catch_unwind;
+use std::thread;
+
+#[test]
+fn test_each_ephemeral_call_gets_its_own_thread() {
+    let shared = call_sync(ComModel::STA, || thread::current().id());
+    let (first, model) = call_sync_ephemeral(ComModel::STA, || {
+        (thread::current().id(), current_apartment())
+    });
+    let second = call_sync_ephemeral(ComModel::STA, || thread::current().id());
+
+    assert_eq!(model, Some(ComModel::STA));
+    assert_ne!(first, second);
+    assert_ne!(first, shared);
+    assert_ne!(first, thread::current().id());
+
+    // a panic ends only the ephemeral thread
+    let err = catch_unwind(|| call_sync_ephemeral(ComModel::MTA, || panic!("isolated")));
+    assert_eq!(err.unwrap_err().downcast_ref::<&str>(), Some(&"isolated"));
+    assert_eq!(call_sync_ephemeral(ComModel::MTA, || 5), 5);
+}
+
+#[test]
+fn test_ephemeral_calls_use_spare_threads() {
+    let rt = ComRuntime::new();
+    rt.configure_apartment(
+        ComModel::MTA,
+        ApartmentConfig::default().ephemeral_spares(2),
+    );
+
+    let ids: Vec<_> = (0..5)
+        .map(|i| {
+            rt.call_sync_ephemeral(ComModel::MTA, move || {
+                assert_eq!(current_apartment(), Some(ComModel::MTA));
+                (i, thread::current().id())
+            })
+        })
+        .collect();
+    for (n, (i, id)) in ids.iter().enumerate() {
+        assert_eq!(n, *i);
+        assert!(ids[n + 1..].iter().all(|(_, other)| other != id));
+    }
+
+    rt.shutdown();
+    assert_eq!(
+        rt.try_call_sync_ephemeral(ComModel::MTA, || ()),
+        Err(CallError::ShutDown(ComModel::MTA))
+    );
+}
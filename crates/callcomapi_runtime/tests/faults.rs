@@ -0,0 +1,82 @@
+#![cfg(feature = "fault-injection")]
+
+use callcomapi_runtime::{ApartmentConfig, CallError, ComModel, ComRuntime, Fault, RestartPolicy};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn runtime() -> ComRuntime {
+    let rt = ComRuntime::new();
+    for model in [ComModel::STA, ComModel::MTA] {
+        rt.configure_apartment(
+            model,
+            ApartmentConfig::default().restart_policy(
+                RestartPolicy::always().backoff(Duration::from_millis(1), Duration::from_millis(1)),
+            ),
+        );
+    }
+    rt
+}
+
+#[test]
+fn test_init_failure_is_restarted() {
+    let rt = runtime();
+    let _fault = rt.inject_fault(Fault::init_fails().times(2));
+
+    assert_eq!(rt.call_sync(ComModel::STA, || 1), 1);
+    assert_eq!(rt.stats().apartment(ComModel::STA).unwrap().restarts, 2);
+}
+
+#[test]
+fn test_worker_dies_mid_queue() {
+    let rt = runtime();
+    let _fault = rt.inject_fault(Fault::worker_dies().on(ComModel::MTA));
+
+    let first = rt.try_call_sync(ComModel::MTA, || thread::current().id());
+    assert_eq!(first, Err(CallError::WorkerLost(ComModel::MTA)));
+
+    // the rest of the queue is served by the restarted worker
+    let (a, b) = thread::scope(|s| {
+        let a = s.spawn(|| rt.call_sync(ComModel::MTA, || 1));
+        let b = s.spawn(|| rt.call_sync(ComModel::MTA, || 2));
+        (a.join().unwrap(), b.join().unwrap())
+    });
+    assert_eq!((a, b), (1, 2));
+    assert_eq!(rt.stats().apartment(ComModel::MTA).unwrap().restarts, 1);
+}
+
+#[test]
+fn test_send_failure_and_guard_scope() {
+    let rt = runtime();
+    let error = CallError::ApartmentFailed(ComModel::STA);
+    let fault = rt.inject_fault(Fault::send_fails(error).always());
+
+    assert_eq!(rt.try_call_sync(ComModel::STA, || 1), Err(error));
+    assert_eq!(
+        futures::executor::block_on(rt.try_call_async(ComModel::STA, || 1)),
+        Err(error)
+    );
+    // other runtimes are unaffected
+    assert_eq!(ComRuntime::new().try_call_sync(ComModel::STA, || 2), Ok(2));
+
+    drop(fault);
+    assert_eq!(rt.try_call_sync(ComModel::STA, || 3), Ok(3));
+}
+
+#[test]
+fn test_delay_and_dropped_result() {
+    let rt = runtime();
+    let _delay = rt.inject_fault(Fault::delay(Duration::from_millis(50)));
+    let start = Instant::now();
+    rt.call_sync(ComModel::STA, || ());
+    assert!(start.elapsed() >= Duration::from_millis(50));
+
+    let _drop = rt.inject_fault(Fault::drop_result());
+    let ran = Arc::new(AtomicBool::new(false));
+    let flag = ran.clone();
+    let res = rt.try_call_sync(ComModel::STA, move || flag.store(true, Ordering::SeqCst));
+    assert_eq!(res, Err(CallError::WorkerLost(ComModel::STA)));
+    assert!(ran.load(Ordering::SeqCst));
+    assert_eq!(rt.try_call_sync(ComModel::STA, || 4), Ok(4));
+}
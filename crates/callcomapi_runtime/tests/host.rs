@@ -0,0 +1,116 @@
+use callcomapi_runtime::{
+    CallError, ComModel, ComRuntime, current_apartment, init_com, is_runtime_worker,
+};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+#[test]
+fn test_hosted_apartment_runs_tasks_from_the_owning_loop() {
+    let rt = Arc::new(ComRuntime::new());
+    let (wake_tx, wake_rx) = mpsc::channel::<()>();
+    let host = rt
+        .host_apartment(ComModel::STA, move || {
+            let _ = wake_tx.send(());
+        })
+        .unwrap();
+    assert!(is_runtime_worker());
+    assert_eq!(host.run_pending(), 0);
+
+    let caller = {
+        let rt = rt.clone();
+        thread::spawn(move || {
+            rt.call_sync(ComModel::STA, || {
+                (thread::current().id(), current_apartment())
+            })
+        })
+    };
+    // stands in for the host's message loop
+    wake_rx.recv().unwrap();
+    assert_eq!(host.run_pending(), 1);
+    let (id, model) = caller.join().unwrap();
+    assert_eq!(id, thread::current().id());
+    assert_eq!(model, Some(ComModel::STA));
+
+    // the other apartment still gets its own worker
+    let mta = rt.call_sync(ComModel::MTA, || thread::current().id());
+    assert_ne!(mta, thread::current().id());
+
+    // a task still queued when the host leaves runs on a spawned worker
+    let queued = {
+        let rt = rt.clone();
+        thread::spawn(move || rt.call_sync(ComModel::STA, || thread::current().id()))
+    };
+    wake_rx.recv().unwrap();
+    drop(host);
+    assert!(!is_runtime_worker());
+    assert_ne!(queued.join().unwrap(), thread::current().id());
+    let id = rt.call_sync(ComModel::STA, || thread::current().id());
+    assert_ne!(id, thread::current().id());
+}
+
+#[test]
+fn test_run_main_sta_serves_calls_from_the_body() {
+    let rt = Arc::new(ComRuntime::new());
+    let main = thread::current().id();
+    let ran = Arc::new(AtomicUsize::new(0));
+
+    let body = {
+        let rt = rt.clone();
+        let ran = ran.clone();
+        move || {
+            assert_ne!(thread::current().id(), main);
+            for _ in 0..3 {
+                let ran = ran.clone();
+                let id = rt.call_sync(ComModel::STA, move || {
+                    ran.fetch_add(1, Ordering::SeqCst);
+                    thread::current().id()
+                });
+                assert_eq!(id, main);
+            }
+            "done"
+        }
+    };
+    assert_eq!(rt.run_main_sta(body), Ok("done"));
+    assert_eq!(ran.load(Ordering::SeqCst), 3);
+    assert_eq!(
+        rt.stats().apartment(ComModel::STA).unwrap().tasks_completed,
+        3
+    );
+}
+
+#[test]
+fn test_apartment_with_a_worker_cannot_be_hosted() {
+    let rt = ComRuntime::new();
+    rt.call_sync(ComModel::STA, || ());
+    assert!(matches!(
+        rt.host_apartment(ComModel::STA, || {}),
+        Err(CallError::AlreadyServed(ComModel::STA))
+    ));
+    assert_eq!(
+        rt.run_main_sta(|| ()),
+        Err(CallError::AlreadyServed(ComModel::STA))
+    );
+    // the worker keeps serving calls
+    assert_eq!(rt.call_sync(ComModel::STA, || 1), 1);
+}
+
+#[test]
+fn test_thread_in_the_other_apartment_cannot_host() {
+    let rt = ComRuntime::new();
+    thread::scope(|s| {
+        s.spawn(|| {
+            let _com = unsafe { init_com(ComModel::MTA) };
+            let Err(CallError::ComInit(ComModel::STA, code)) =
+                rt.host_apartment(ComModel::STA, || {})
+            else {
+                panic!("hosted an STA on an MTA thread");
+            };
+            assert!(code.is_err());
+            assert!(!is_runtime_worker());
+        });
+    });
+    // nothing was taken over
+    assert_eq!(rt.call_sync(ComModel::STA, || 1), 1);
+}
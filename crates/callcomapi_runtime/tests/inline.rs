@@ -0,0 +1,91 @@
+use callcomapi_runtime::{
+    ComModel, ComRuntime, call_async, call_stream, call_sync, inline_guard, spawn_detached,
+};
+use futures::StreamExt;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[test]
+fn test_inline_runtime_runs_tasks_on_caller() {
+    let rt = ComRuntime::new();
+    rt.set_inline(true);
+    let caller = thread::current().id();
+
+    assert_eq!(
+        rt.call_sync(ComModel::STA, || thread::current().id()),
+        caller
+    );
+    // async tasks complete when submitted
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let sink = log.clone();
+    let fut = rt.call_async(ComModel::STA, move || sink.lock().unwrap().push("ran"));
+    assert_eq!(*log.lock().unwrap(), ["ran"]);
+    futures::executor::block_on(fut);
+
+    let panic = catch_unwind(AssertUnwindSafe(|| {
+        rt.call_sync(ComModel::STA, || panic!("inline boom"))
+    }))
+    .unwrap_err();
+    assert_eq!(panic.downcast_ref::<&str>(), Some(&"inline boom"));
+    assert_eq!(rt.call_sync(ComModel::STA, || 7), 7);
+
+    let sta = rt.stats().apartment(ComModel::STA).cloned().unwrap();
+    assert_eq!(sta.tasks_completed, 3);
+    assert_eq!(sta.restarts, 0);
+    assert!(sta.running.is_none());
+}
+
+#[test]
+fn test_inline_guard_is_scoped_to_thread() {
+    let caller = thread::current().id();
+    {
+        let _guard = inline_guard();
+        assert_eq!(call_sync(ComModel::STA, || thread::current().id()), caller);
+        assert_eq!(
+            futures::executor::block_on(call_async(ComModel::MTA, || thread::current().id())),
+            caller
+        );
+
+        let ran = Arc::new(Mutex::new(false));
+        let flag = ran.clone();
+        spawn_detached(ComModel::STA, move || *flag.lock().unwrap() = true);
+        assert!(*ran.lock().unwrap());
+
+        // producers may run ahead of the consumer without blocking
+        let items: Vec<u32> = futures::executor::block_on(
+            call_stream(ComModel::STA, |sink| {
+                for i in 0..100 {
+                    sink.send(i).unwrap();
+                }
+            })
+            .collect(),
+        );
+        assert_eq!(items, (0..100).collect::<Vec<_>>());
+
+        // other threads are unaffected
+        let other = thread::spawn(|| {
+            (
+                thread::current().id(),
+                call_sync(ComModel::STA, || thread::current().id()),
+            )
+        })
+        .join()
+        .unwrap();
+        assert_ne!(other.0, other.1);
+    }
+    assert_ne!(call_sync(ComModel::STA, || thread::current().id()), caller);
+}
+
+#[test]
+fn test_inline_async_body_inside_an_executor() {
+    let _guard = inline_guard();
+    // what `#[com_thread] async fn` expands to, awaited from an executor
+    let id = futures::executor::block_on(async {
+        call_async(ComModel::MTA, || {
+            callcomapi_runtime::block_on(async { thread::current().id() })
+        })
+        .await
+    });
+    assert_eq!(id, thread::current().id());
+}
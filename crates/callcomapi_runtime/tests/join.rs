@@ -0,0 +1,83 @@
+use callcomapi_runtime::{ApartmentJoinSet, CallError, ComModel, ComRuntime, current_apartment};
+use futures::executor::block_on;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::Duration;
+
+#[test]
+fn test_join_set_yields_in_completion_order() {
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let mut set = ApartmentJoinSet::new();
+    set.spawn(ComModel::STA, move || {
+        release_rx.recv().unwrap();
+        ("slow", current_apartment())
+    });
+    set.spawn(ComModel::MTA, || ("fast", current_apartment()));
+    assert_eq!(set.len(), 2);
+
+    block_on(async {
+        let first = set.join_next().await.unwrap().unwrap();
+        assert_eq!(first, ("fast", Some(ComModel::MTA)));
+        release_tx.send(()).unwrap();
+        let second = set.join_next().await.unwrap().unwrap();
+        assert_eq!(second, ("slow", Some(ComModel::STA)));
+        assert!(set.join_next().await.is_none());
+    });
+}
+
+#[test]
+fn test_join_all_keeps_spawn_order() {
+    let rt = ComRuntime::new();
+    let mut set = rt.join_set();
+    for i in 0..10 {
+        let model = if i % 2 == 0 {
+            ComModel::STA
+        } else {
+            ComModel::MTA
+        };
+        set.spawn(model, move || i * 2);
+    }
+    let results: Vec<_> = block_on(set.join_all())
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(results, (0..10).map(|i| i * 2).collect::<Vec<_>>());
+
+    rt.shutdown();
+    let mut set = rt.join_set();
+    set.spawn(ComModel::MTA, || ());
+    assert_eq!(
+        block_on(set.select()),
+        Some(Err(CallError::ShutDown(ComModel::MTA)))
+    );
+}
+
+#[test]
+fn test_dropping_the_set_cancels_unstarted_tasks() {
+    let rt = ComRuntime::new();
+    let ran = Arc::new(AtomicUsize::new(0));
+    let (started_tx, started_rx) = mpsc::channel::<()>();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+
+    let mut set = rt.join_set();
+    set.spawn(ComModel::STA, move || {
+        started_tx.send(()).unwrap();
+        release_rx.recv().unwrap();
+    });
+    for _ in 0..3 {
+        let ran = ran.clone();
+        set.spawn(ComModel::STA, move || {
+            ran.fetch_add(1, Ordering::SeqCst);
+        });
+    }
+    started_rx.recv().unwrap();
+    drop(set);
+    release_tx.send(()).unwrap();
+
+    // the queue is drained in order, so this runs after the cancelled tasks
+    rt.call_sync(ComModel::STA, || ());
+    assert_eq!(ran.load(Ordering::SeqCst), 0);
+    std::thread::sleep(Duration::from_millis(10));
+    assert_eq!(ran.load(Ordering::SeqCst), 0);
+}
@@ -0,0 +1,209 @@
+use callcomapi_runtime::{
+    __key_hash, ApartmentConfig, ApartmentState, CallError, ComModel, ComRuntime, HungTaskPolicy,
+    RestartPolicy, WatchdogConfig, call_keyed, current_apartment,
+};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::{Arc, mpsc};
+use std::thread;
+use std::time::Duration;
+
+fn pooled(workers: usize) -> Arc<ComRuntime> {
+    let rt = ComRuntime::new();
+    rt.configure_apartment(
+        ComModel::MTA,
+        ApartmentConfig::default().keyed_workers(workers),
+    );
+    Arc::new(rt)
+}
+
+#[test]
+fn test_keyed_calls_share_the_worker_without_a_pool() {
+    let worker = callcomapi_runtime::call_sync(ComModel::STA, || thread::current().id());
+    let keyed = call_keyed(ComModel::STA, "host-a", || thread::current().id());
+    assert_eq!(keyed, worker);
+}
+
+#[test]
+fn test_same_key_same_worker_in_order() {
+    let rt = pooled(4);
+    let first = __key_hash("host-a") % 4;
+    let other = (0..)
+        .map(|i| format!("host-{i}"))
+        .find(|key| __key_hash(key.as_str()) % 4 != first)
+        .unwrap();
+
+    let ids: Vec<_> = (0..5)
+        .map(|_| {
+            rt.call_keyed(ComModel::MTA, "host-a", || {
+                (thread::current().id(), current_apartment())
+            })
+        })
+        .collect();
+    assert!(ids.iter().all(|id| *id == ids[0]));
+    assert_eq!(ids[0].1, Some(ComModel::MTA));
+
+    // a second call on the same key waits for the first, another key does not
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let (started_tx, started_rx) = mpsc::channel::<()>();
+    let blocked = {
+        let rt = rt.clone();
+        thread::spawn(move || {
+            rt.call_keyed(ComModel::MTA, "host-a", move || {
+                started_tx.send(()).unwrap();
+                release_rx.recv().unwrap();
+            })
+        })
+    };
+    started_rx.recv().unwrap();
+    let (order_tx, order_rx) = mpsc::channel();
+    let queued = {
+        let rt = rt.clone();
+        let order_tx = order_tx.clone();
+        thread::spawn(move || {
+            rt.call_keyed(ComModel::MTA, "host-a", move || {
+                order_tx.send("same key").unwrap()
+            })
+        })
+    };
+    let id = rt.call_keyed(ComModel::MTA, other, move || {
+        order_tx.send("other key").unwrap();
+        thread::current().id()
+    });
+    assert_ne!(id, ids[0].0);
+    assert_eq!(order_rx.recv().unwrap(), "other key");
+    assert!(order_rx.recv_timeout(Duration::from_millis(20)).is_err());
+
+    release_tx.send(()).unwrap();
+    blocked.join().unwrap();
+    queued.join().unwrap();
+    assert_eq!(order_rx.recv().unwrap(), "same key");
+}
+
+#[test]
+fn test_keyed_worker_survives_a_panic() {
+    let rt = pooled(2);
+    let before = rt.call_keyed(ComModel::MTA, 7, || thread::current().id());
+    let err = catch_unwind(AssertUnwindSafe(|| {
+        rt.call_keyed(ComModel::MTA, 7, || panic!("query failed"))
+    }));
+    assert!(err.is_err());
+    let after = rt.call_keyed(ComModel::MTA, 7, || thread::current().id());
+    assert_ne!(before, after);
+    assert_eq!(rt.stats().apartment(ComModel::MTA).unwrap().restarts, 1);
+
+    rt.shutdown();
+    assert_eq!(
+        rt.try_call_keyed(ComModel::MTA, 7, || ()),
+        Err(CallError::ShutDown(ComModel::MTA))
+    );
+}
+
+#[test]
+fn test_keyed_workers_follow_the_restart_policy() {
+    let rt = ComRuntime::new();
+    rt.configure_apartment(
+        ComModel::STA,
+        ApartmentConfig::default()
+            .keyed_workers(2)
+            .restart_policy(RestartPolicy::never()),
+    );
+    let crash = catch_unwind(AssertUnwindSafe(|| {
+        rt.call_keyed(ComModel::STA, "host-a", || panic!("query failed"))
+    }));
+    assert!(crash.is_err());
+
+    // the crash fails the whole apartment instead of respawning the lane
+    assert_eq!(
+        rt.try_call_keyed(ComModel::STA, "host-a", || ()),
+        Err(CallError::ApartmentFailed(ComModel::STA))
+    );
+    let sta = rt.stats().apartment(ComModel::STA).cloned().unwrap();
+    assert_eq!(sta.state, ApartmentState::Failed);
+    assert_eq!(sta.restarts, 0);
+}
+
+#[test]
+fn test_watchdog_replaces_a_hung_keyed_worker() {
+    let rt = pooled(2);
+    rt.enable_watchdog(
+        WatchdogConfig::new(Duration::from_millis(50))
+            .poll_interval(Duration::from_millis(10))
+            .policy(HungTaskPolicy::Replace),
+    );
+    let hung_tid = rt.call_keyed(ComModel::MTA, "host-a", || thread::current().id());
+    let stuck = {
+        let rt = rt.clone();
+        thread::spawn(move || {
+            rt.call_keyed(ComModel::MTA, "host-a", || {
+                thread::sleep(Duration::from_millis(400));
+                thread::current().id()
+            })
+        })
+    };
+
+    // later calls on the key run on a fresh worker
+    thread::sleep(Duration::from_millis(150));
+    let fresh_tid = rt.call_keyed(ComModel::MTA, "host-a", || thread::current().id());
+    assert_ne!(fresh_tid, hung_tid);
+
+    let mta = rt.stats().apartment(ComModel::MTA).cloned().unwrap();
+    assert_eq!((mta.hung_tasks, mta.replacements), (1, 1));
+    assert_eq!(stuck.join().unwrap(), hung_tid);
+    rt.disable_watchdog();
+}
+
+#[test]
+fn test_calls_behind_a_failing_keyed_worker_get_apartment_failed() {
+    let rt = Arc::new(ComRuntime::new());
+    rt.configure_apartment(
+        ComModel::MTA,
+        ApartmentConfig::default()
+            .keyed_workers(2)
+            .restart_policy(RestartPolicy::never()),
+    );
+    // the crashing key on the last lane, so an idle worker serves a lane
+    // in front of it
+    let key_on = |lane| {
+        (0..)
+            .map(|i| format!("host-{i}"))
+            .find(|key| __key_hash(key.as_str()) % 2 == lane)
+            .unwrap()
+    };
+    let (idle, key) = (key_on(0), key_on(1));
+    rt.call_keyed(ComModel::MTA, idle, || ());
+
+    let (started_tx, started_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let crashing = {
+        let (rt, key) = (rt.clone(), key.clone());
+        thread::spawn(move || {
+            catch_unwind(AssertUnwindSafe(|| {
+                rt.call_keyed(ComModel::MTA, key, move || {
+                    started_tx.send(()).unwrap();
+                    release_rx.recv().unwrap();
+                    panic!("query failed")
+                })
+            }))
+        })
+    };
+    started_rx.recv().unwrap();
+    let (queued_tx, queued_rx) = mpsc::channel();
+    {
+        let rt = rt.clone();
+        thread::spawn(move || {
+            let _ = queued_tx.send(rt.try_call_keyed(ComModel::MTA, key, || ()));
+        });
+    }
+    thread::sleep(Duration::from_millis(20));
+    release_tx.send(()).unwrap();
+
+    assert!(crashing.join().unwrap().is_err());
+    assert_eq!(
+        queued_rx.recv_timeout(Duration::from_secs(5)).unwrap(),
+        Err(CallError::ApartmentFailed(ComModel::MTA))
+    );
+    assert_eq!(
+        rt.stats().apartment(ComModel::MTA).unwrap().state,
+        ApartmentState::Failed
+    );
+}
@@ -0,0 +1,50 @@
+use callcomapi_runtime::{
+    ApartmentConfig, ComModel, call_async, call_sync, configure_apartment, stats,
+};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn test_worker_recycled_after_task_limit() {
+    configure_apartment(
+        ComModel::STA,
+        ApartmentConfig::default().recycle_after_tasks(2),
+    );
+
+    let tids: Vec<_> = (0..6)
+        .map(|_| call_sync(ComModel::STA, || thread::current().id()))
+        .collect();
+    assert_eq!(tids[0], tids[1]);
+    assert_ne!(tids[1], tids[2]);
+    assert_eq!(tids[2], tids[3]);
+    assert_ne!(tids[3], tids[4]);
+
+    // tasks queued behind a retiring worker move to its successor
+    let pending: Vec<_> = (0..10)
+        .map(|i| call_async(ComModel::STA, move || i * 2))
+        .collect();
+    let results: Vec<_> = pending
+        .into_iter()
+        .map(futures::executor::block_on)
+        .collect();
+    assert_eq!(results, (0..10).map(|i| i * 2).collect::<Vec<_>>());
+
+    let sta = stats().apartment(ComModel::STA).cloned().unwrap();
+    assert!(sta.tasks_completed >= 15);
+    assert!(sta.recycles >= 7);
+    assert_eq!(sta.restarts, 0);
+}
+
+#[test]
+fn test_idle_worker_recycled_after_age() {
+    configure_apartment(
+        ComModel::MTA,
+        ApartmentConfig::default().recycle_after_age(Duration::from_millis(50)),
+    );
+
+    let first = call_sync(ComModel::MTA, || thread::current().id());
+    thread::sleep(Duration::from_millis(150));
+    assert!(stats().apartment(ComModel::MTA).unwrap().recycles >= 1);
+    let second = call_sync(ComModel::MTA, || thread::current().id());
+    assert_ne!(first, second);
+}
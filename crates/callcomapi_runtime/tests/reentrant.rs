@@ -0,0 +1,42 @@
+use callcomapi_runtime::{CallError, ComModel, ComRuntime, call_sync_reentrant, current_apartment};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::thread;
+
+#[test]
+fn test_worker_calls_back_into_the_waiting_caller() {
+    let caller = thread::current().id();
+    let (worker, answers) = call_sync_reentrant(ComModel::STA, move |handle| {
+        let answers: Vec<_> = (0..3)
+            .map(|i| handle.call(move || (i, thread::current().id() == caller)))
+            .collect();
+        ((thread::current().id(), current_apartment()), answers)
+    });
+
+    assert_ne!(worker.0, caller);
+    assert_eq!(worker.1, Some(ComModel::STA));
+    assert_eq!(answers, [(0, true), (1, true), (2, true)]);
+}
+
+#[test]
+fn test_callback_panic_unwinds_the_task() {
+    let rt = ComRuntime::new();
+    let err = catch_unwind(AssertUnwindSafe(|| {
+        rt.call_sync_reentrant(ComModel::MTA, |handle| {
+            handle.call(|| panic!("prompt failed"));
+        })
+    }));
+    assert_eq!(
+        err.unwrap_err().downcast_ref::<&str>(),
+        Some(&"prompt failed")
+    );
+    assert_eq!(rt.call_sync_reentrant(ComModel::MTA, |h| h.call(|| 4)), 4);
+
+    rt.set_inline(true);
+    assert_eq!(rt.call_sync_reentrant(ComModel::STA, |h| h.call(|| 5)), 5);
+
+    rt.shutdown();
+    assert_eq!(
+        rt.try_call_sync_reentrant(ComModel::MTA, |_| ()),
+        Err(CallError::ShutDown(ComModel::MTA))
+    );
+}
@@ -0,0 +1,64 @@
+use callcomapi_runtime::{
+    ApartmentConfig, ApartmentState, CallError, ComModel, RestartPolicy, RuntimeEvent, call_sync,
+    configure_apartment, set_event_handler, stats, try_call_sync,
+};
+use std::panic::catch_unwind;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+fn boom() {
+    panic!("boom");
+}
+
+#[test]
+fn test_restart_policy_limits_restarts() {
+    let events: Arc<Mutex<Vec<RuntimeEvent>>> = Arc::default();
+    let sink = events.clone();
+    set_event_handler(move |event| sink.lock().unwrap().push(event.clone()));
+
+    // STA: two restarts allowed, the third crash fails the apartment
+    configure_apartment(
+        ComModel::STA,
+        ApartmentConfig::default().restart_policy(
+            RestartPolicy::limited(2, Duration::from_secs(60))
+                .backoff(Duration::from_millis(1), Duration::from_millis(4)),
+        ),
+    );
+    for _ in 0..3 {
+        let err = catch_unwind(|| call_sync(ComModel::STA, boom)).unwrap_err();
+        assert_eq!(err.downcast_ref::<&str>(), Some(&"boom"));
+    }
+    assert_eq!(
+        try_call_sync(ComModel::STA, || 1),
+        Err(CallError::ApartmentFailed(ComModel::STA))
+    );
+
+    let sta = stats().apartment(ComModel::STA).cloned().unwrap();
+    assert_eq!(sta.state, ApartmentState::Failed);
+    assert_eq!(sta.restarts, 2);
+    assert!(!sta.healthy);
+
+    // MTA: the default policy keeps restarting, with a fresh worker each time
+    let before = call_sync(ComModel::MTA, || thread::current().id());
+    assert!(catch_unwind(|| call_sync(ComModel::MTA, boom)).is_err());
+    let after = call_sync(ComModel::MTA, || thread::current().id());
+    assert_ne!(before, after);
+    assert_eq!(stats().apartment(ComModel::MTA).unwrap().restarts, 1);
+
+    let events = events.lock().unwrap();
+    let restarted = |model| {
+        events
+            .iter()
+            .filter(|e| matches!(e, RuntimeEvent::WorkerRestarted { model: m, .. } if *m == model))
+            .count()
+    };
+    assert_eq!(restarted(ComModel::STA), 2);
+    assert_eq!(restarted(ComModel::MTA), 1);
+    assert!(events.iter().any(|e| matches!(
+        e,
+        RuntimeEvent::ApartmentFailed {
+            model: ComModel::STA
+        }
+    )));
+}
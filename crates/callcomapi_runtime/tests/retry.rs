@@ -0,0 +1,116 @@
+use callcomapi_runtime::{ComModel, ComRuntime, RetryPolicy, call_with_retry_async};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+use windows::core::{Error, HRESULT};
+
+const RPC_E_CALL_REJECTED: HRESULT = HRESULT(0x8001_0001_u32 as i32);
+const E_ACCESSDENIED: HRESULT = HRESULT(0x8007_0005_u32 as i32);
+
+/// A call failing with `code` until its `succeed_on`-th attempt.
+fn flaky(
+    code: HRESULT,
+    succeed_on: u32,
+) -> (
+    Arc<AtomicU32>,
+    impl Fn() -> windows::core::Result<u32> + Send + Sync + 'static,
+) {
+    let attempts = Arc::new(AtomicU32::new(0));
+    let counter = attempts.clone();
+    let f = move || {
+        let attempt = counter.fetch_add(1, Ordering::SeqCst) + 1;
+        if attempt < succeed_on {
+            Err(Error::from(code))
+        } else {
+            Ok(attempt)
+        }
+    };
+    (attempts, f)
+}
+
+#[test]
+fn test_transient_failures_are_retried() {
+    let rt = ComRuntime::new();
+    let policy = RetryPolicy::exponential(3, Duration::from_millis(10), Duration::from_millis(20));
+    let (attempts, f) = flaky(RPC_E_CALL_REJECTED, 3);
+    let started = Instant::now();
+    assert_eq!(rt.call_with_retry(ComModel::MTA, policy, f).unwrap(), 3);
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    assert!(started.elapsed() >= Duration::from_millis(30));
+
+    // retries used up: the last error is returned
+    let (attempts, f) = flaky(RPC_E_CALL_REJECTED, 10);
+    let error = rt
+        .call_with_retry(ComModel::MTA, RetryPolicy::immediate(2), f)
+        .unwrap_err();
+    assert_eq!(error.code(), RPC_E_CALL_REJECTED);
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn test_other_failures_are_returned_at_once() {
+    let rt = ComRuntime::new();
+    let (attempts, f) = flaky(E_ACCESSDENIED, 2);
+    let error = rt
+        .call_with_retry(ComModel::STA, RetryPolicy::immediate(3), f)
+        .unwrap_err();
+    assert_eq!(error.code(), E_ACCESSDENIED);
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+    let policy = RetryPolicy::immediate(3).classify(|code| code == E_ACCESSDENIED);
+    let (_, f) = flaky(E_ACCESSDENIED, 2);
+    assert_eq!(rt.call_with_retry(ComModel::STA, policy, f).unwrap(), 2);
+}
+
+#[test]
+fn test_worker_is_free_between_attempts() {
+    let rt = ComRuntime::new();
+    let policy = RetryPolicy::fixed(1, Duration::from_millis(200));
+    let (_, f) = flaky(RPC_E_CALL_REJECTED, 2);
+    std::thread::scope(|s| {
+        let retried = s.spawn(|| rt.call_with_retry(ComModel::STA, policy, f));
+        std::thread::sleep(Duration::from_millis(50));
+        let started = Instant::now();
+        rt.call_sync(ComModel::STA, || ());
+        assert!(started.elapsed() < Duration::from_millis(100));
+        assert_eq!(retried.join().unwrap().unwrap(), 2);
+    });
+}
+
+#[test]
+fn test_retry_async() {
+    let (attempts, f) = flaky(RPC_E_CALL_REJECTED, 2);
+    let res = futures::executor::block_on(call_with_retry_async(
+        ComModel::STA,
+        RetryPolicy::fixed(2, Duration::from_millis(5)),
+        f,
+    ));
+    assert_eq!(res.unwrap(), 2);
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_retry_async_outlives_the_borrow_and_fails_on_shutdown() {
+    const RPC_E_DISCONNECTED: HRESULT = HRESULT(0x8001_0108_u32 as i32);
+    let rt = ComRuntime::new();
+    let (attempts, f) = flaky(RPC_E_CALL_REJECTED, 10);
+    let retried = rt.call_with_retry_async(
+        ComModel::MTA,
+        RetryPolicy::fixed(5, Duration::from_millis(100)),
+        f,
+    );
+    let retried = std::thread::spawn(move || futures::executor::block_on(retried));
+
+    // shutting down while a retry waits ends the call with an error
+    std::thread::sleep(Duration::from_millis(50));
+    rt.shutdown();
+    let error = retried.join().unwrap().unwrap_err();
+    assert_eq!(error.code(), RPC_E_DISCONNECTED);
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+    let (_, f) = flaky(RPC_E_CALL_REJECTED, 1);
+    let error = rt
+        .call_with_retry(ComModel::MTA, RetryPolicy::immediate(1), f)
+        .unwrap_err();
+    assert_eq!(error.code(), RPC_E_DISCONNECTED);
+}
@@ -0,0 +1,72 @@
+use callcomapi_runtime::{
+    ApartmentConfig, ApartmentState, CallError, ComModel, ComRuntime, RestartPolicy,
+};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn test_runtimes_keep_separate_apartments() {
+    let a = ComRuntime::new();
+    let b = ComRuntime::new();
+
+    let a_sta = a.call_sync(ComModel::STA, || thread::current().id());
+    let b_sta = b.call_sync(ComModel::STA, || thread::current().id());
+    let global_sta = ComRuntime::global().call_sync(ComModel::STA, || thread::current().id());
+    assert_ne!(a_sta, b_sta);
+    assert_ne!(a_sta, global_sta);
+    assert_ne!(b_sta, global_sta);
+
+    // a crash under a strict policy only fails the runtime it happened in
+    a.configure_apartment(
+        ComModel::STA,
+        ApartmentConfig::default().restart_policy(RestartPolicy::never()),
+    );
+    let crash = catch_unwind(AssertUnwindSafe(|| {
+        a.call_sync(ComModel::STA, || panic!("boom"))
+    }));
+    assert!(crash.is_err());
+    assert_eq!(
+        a.try_call_sync(ComModel::STA, || 1),
+        Err(CallError::ApartmentFailed(ComModel::STA))
+    );
+    assert_eq!(b.call_sync(ComModel::STA, || 2), 2);
+
+    assert_eq!(a.stats().apartments.len(), 1);
+    assert_eq!(
+        b.stats().apartment(ComModel::STA).unwrap().state,
+        ApartmentState::Running
+    );
+}
+
+#[test]
+fn test_shutdown_drains_queue_and_rejects_new_work() {
+    let rt = ComRuntime::new();
+    let (tx, rx) = mpsc::channel();
+
+    let first = tx.clone();
+    rt.spawn_detached(ComModel::MTA, move || {
+        thread::sleep(Duration::from_millis(50));
+        first.send(1).unwrap();
+    });
+    rt.spawn_detached(ComModel::MTA, move || tx.send(2).unwrap());
+    rt.shutdown();
+
+    assert_eq!(
+        rt.try_call_sync(ComModel::MTA, || ()),
+        Err(CallError::ShutDown(ComModel::MTA))
+    );
+    assert_eq!(
+        futures::executor::block_on(rt.try_call_async(ComModel::STA, || ())),
+        Err(CallError::ShutDown(ComModel::STA))
+    );
+
+    // work queued before the shutdown still runs, in order
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(1));
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(2));
+    assert_eq!(
+        rt.stats().apartment(ComModel::MTA).unwrap().state,
+        ApartmentState::ShutDown
+    );
+}
@@ -0,0 +1,37 @@
+use callcomapi_runtime::{ComModel, call_sync_scoped};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::thread;
+
+#[test]
+fn test_scoped_call_borrows_caller_data() {
+    let name = String::from("processor");
+    let bytes = [1u8, 2, 3];
+    let mut seen = Vec::new();
+
+    let (len, sum, tid) = call_sync_scoped(ComModel::STA, || {
+        seen.push(name.as_str().to_owned());
+        (
+            name.len(),
+            bytes.iter().map(|b| *b as u32).sum::<u32>(),
+            thread::current().id(),
+        )
+    });
+
+    assert_eq!(len, 9);
+    assert_eq!(sum, 6);
+    assert_ne!(tid, thread::current().id());
+    assert_eq!(seen, ["processor"]);
+}
+
+#[test]
+fn test_scoped_call_propagates_panic() {
+    let message = String::from("boom");
+    let res = catch_unwind(AssertUnwindSafe(|| {
+        call_sync_scoped::<_, ()>(ComModel::MTA, || panic!("{}", message))
+    }));
+    assert!(res.is_err());
+
+    // the apartment keeps serving scoped calls after the panic
+    let doubled = call_sync_scoped(ComModel::MTA, || message.repeat(2));
+    assert_eq!(doubled, "boomboom");
+}
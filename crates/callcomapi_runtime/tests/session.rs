@@ -0,0 +1,95 @@
+use callcomapi_runtime::{
+    CallError, ComModel, ComRuntime, SessionClosed, SessionReceiver, SessionSender,
+    current_apartment, open_session,
+};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::mpsc;
+use std::thread;
+
+#[test]
+fn test_session_exchanges_messages_with_one_handler() {
+    let (done_tx, done_rx) = mpsc::channel();
+    let mut session = open_session(ComModel::STA, move |requests, responses| {
+        let worker = thread::current().id();
+        let mut total = 0;
+        for n in requests {
+            total += n;
+            responses
+                .send((total, worker, current_apartment()))
+                .unwrap();
+        }
+        done_tx.send(total).unwrap();
+    });
+
+    let (first, worker, model) = session.request(1).unwrap();
+    assert_eq!(first, 1);
+    assert_eq!(model, Some(ComModel::STA));
+    for n in 2..=4 {
+        let (total, id, _) = session.request(n).unwrap();
+        assert_eq!(id, worker);
+        assert_eq!(total, (1..=n).sum::<i32>());
+    }
+
+    session.close();
+    assert_eq!(done_rx.recv().unwrap(), 10);
+}
+
+#[test]
+fn test_handler_ending_closes_the_session() {
+    let rt = ComRuntime::new();
+    let mut session = rt.open_session(ComModel::MTA, |requests, responses| {
+        let first: String = requests.recv().unwrap();
+        responses.send(first.len()).unwrap();
+    });
+    assert_eq!(session.request("hello".to_string()), Some(5));
+    assert_eq!(session.recv(), None);
+    assert_eq!(session.send("again".to_string()), Err(SessionClosed));
+
+    // dropping the caller side ends the handler's loop
+    let (tx, rx) = mpsc::channel();
+    let session = rt.open_session(ComModel::MTA, move |requests, responses| {
+        let seen = requests.count();
+        tx.send((seen, responses.send(()))).unwrap();
+    });
+    session.send(1).unwrap();
+    session.send(2).unwrap();
+    drop(session);
+    assert_eq!(rx.recv().unwrap(), (2, Err(SessionClosed)));
+
+    // sessions still use the worker when tasks run inline
+    rt.set_inline(true);
+    let mut session = rt.open_session(ComModel::STA, |requests, responses| {
+        for n in requests {
+            responses.send(n * 2).unwrap();
+        }
+    });
+    assert_eq!(session.request(21), Some(42));
+}
+
+#[test]
+fn test_handler_panic_is_raised_after_its_responses() {
+    let mut session = open_session(
+        ComModel::STA,
+        |_requests: SessionReceiver<()>, responses| {
+            responses.send(1).unwrap();
+            panic!("document closed");
+        },
+    );
+    assert_eq!(session.recv(), Some(1));
+    let err = catch_unwind(AssertUnwindSafe(|| session.recv()));
+    assert_eq!(
+        err.unwrap_err().downcast_ref::<&str>(),
+        Some(&"document closed")
+    );
+}
+
+#[test]
+fn test_try_open_session_reports_a_shut_down_apartment() {
+    let rt = ComRuntime::new();
+    rt.shutdown();
+    let session = rt.try_open_session(
+        ComModel::STA,
+        |_: SessionReceiver<()>, _: SessionSender<()>| {},
+    );
+    assert!(matches!(session, Err(CallError::ShutDown(ComModel::STA))));
+}
@@ -0,0 +1,157 @@
+use callcomapi_runtime::{ComModel, ComRuntime, call_single_flight, call_single_flight_async};
+use std::hash::{Hash, Hasher};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+
+/// Start a flight for `key` whose task holds the MTA worker until the
+/// returned sender fires or is dropped.
+fn start_gated<R, F>(
+    rt: &Arc<ComRuntime>,
+    key: &'static str,
+    f: F,
+) -> (thread::JoinHandle<R>, mpsc::Sender<()>)
+where
+    R: Clone + Send + Sync + 'static,
+    F: FnOnce() -> R + Send + 'static,
+{
+    let (started_tx, started_rx) = mpsc::channel();
+    let (gate_tx, gate_rx) = mpsc::channel::<()>();
+    let rt = rt.clone();
+    let leader = thread::spawn(move || {
+        rt.call_single_flight_labeled(ComModel::MTA, key, "query", move || {
+            started_tx.send(()).unwrap();
+            let _ = gate_rx.recv();
+            f()
+        })
+    });
+    started_rx.recv().unwrap();
+    (leader, gate_tx)
+}
+
+fn join_flight<R, F>(
+    rt: &Arc<ComRuntime>,
+    key: &'static str,
+    f: F,
+) -> thread::JoinHandle<thread::Result<R>>
+where
+    R: Clone + Send + Sync + 'static,
+    F: FnOnce() -> R + Send + 'static,
+{
+    let rt = rt.clone();
+    thread::spawn(move || {
+        catch_unwind(AssertUnwindSafe(|| {
+            rt.call_single_flight_labeled(ComModel::MTA, key, "query", f)
+        }))
+    })
+}
+
+#[test]
+fn test_concurrent_identical_calls_share_one_run() {
+    let rt = Arc::new(ComRuntime::new());
+    let runs = Arc::new(AtomicUsize::new(0));
+    let counted = |value: &'static str| {
+        let runs = runs.clone();
+        move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            value.to_string()
+        }
+    };
+
+    let (leader, gate) = start_gated(&rt, "cpu", counted("shared"));
+    let followers: Vec<_> = (0..4)
+        .map(|_| join_flight(&rt, "cpu", counted("own")))
+        .collect();
+    thread::sleep(Duration::from_millis(20));
+    gate.send(()).unwrap();
+
+    assert_eq!(leader.join().unwrap(), "shared");
+    for follower in followers {
+        assert_eq!(follower.join().unwrap().unwrap(), "shared");
+    }
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+    // once finished, the next call runs again, as do other keys
+    let again = rt.call_single_flight_labeled(ComModel::MTA, "cpu", "query", counted("again"));
+    assert_eq!(again, "again");
+    let disk = rt.call_single_flight_labeled(ComModel::MTA, "disk", "query", counted("disk"));
+    assert_eq!(disk, "disk");
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+}
+
+/// A key whose values all hash alike.
+#[derive(PartialEq, Eq)]
+struct Colliding(&'static str);
+
+impl Hash for Colliding {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u8(0);
+    }
+}
+
+#[test]
+fn test_keys_with_equal_hashes_run_separately() {
+    let rt = Arc::new(ComRuntime::new());
+    let (tx, rx) = mpsc::channel();
+    let leader = {
+        let rt = rt.clone();
+        thread::spawn(move || {
+            rt.call_single_flight_labeled(ComModel::STA, Colliding("cpu"), "query", move || {
+                rx.recv_timeout(Duration::from_secs(1))
+                    .unwrap_or("timed out")
+            })
+        })
+    };
+    thread::sleep(Duration::from_millis(20));
+    // joining the flight for "cpu" would block on its task instead
+    let disk = rt.call_single_flight_labeled(ComModel::MTA, Colliding("disk"), "query", || "disk");
+    assert_eq!(disk, "disk");
+    tx.send("cpu").unwrap();
+    assert_eq!(leader.join().unwrap(), "cpu");
+}
+
+#[test]
+fn test_panic_reaches_every_waiter() {
+    let rt = Arc::new(ComRuntime::new());
+    let (leader, gate) = start_gated(&rt, "flaky", || -> u32 { panic!("wmi unavailable") });
+    let followers: Vec<_> = (0..3).map(|_| join_flight(&rt, "flaky", || 0u32)).collect();
+    thread::sleep(Duration::from_millis(20));
+    drop(gate);
+
+    let payload = leader.join().unwrap_err();
+    assert_eq!(payload.downcast_ref::<String>().unwrap(), "wmi unavailable");
+    for follower in followers {
+        let payload = follower.join().unwrap().unwrap_err();
+        assert_eq!(payload.downcast_ref::<String>().unwrap(), "wmi unavailable");
+    }
+}
+
+#[test]
+fn test_single_flight_async() {
+    let runs = Arc::new(AtomicUsize::new(0));
+    let (started_tx, started_rx) = mpsc::channel();
+    let (gate_tx, gate_rx) = mpsc::channel::<()>();
+    let gate = Arc::new(Mutex::new(gate_rx));
+    let call = || {
+        let (runs, started, gate) = (runs.clone(), started_tx.clone(), gate.clone());
+        call_single_flight_async(ComModel::STA, "volumes", move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            started.send(()).unwrap();
+            let _ = gate.lock().unwrap().recv();
+            vec![1, 2, 3]
+        })
+    };
+    // the second call joins while the first one's task is running
+    let first = call();
+    started_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    let second = call();
+    gate_tx.send(()).unwrap();
+
+    let (a, b) = futures::executor::block_on(futures::future::join(first, second));
+    assert_eq!(a, [1, 2, 3]);
+    assert_eq!(a, b);
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+    assert_eq!(call_single_flight(ComModel::STA, "count", || 5), 5);
+}
@@ -0,0 +1,44 @@
+use callcomapi_runtime::{ComModel, call_stream};
+use futures::StreamExt;
+use futures::executor::block_on;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+#[test]
+fn test_call_stream_yields_items_in_order() {
+    let stream = call_stream(ComModel::STA, |sink| {
+        for i in 0..100 {
+            sink.send(i).unwrap();
+        }
+    });
+    let items: Vec<i32> = block_on(stream.collect());
+    assert_eq!(items, (0..100).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_call_stream_stops_producer_when_dropped() {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut stream = call_stream(ComModel::MTA, move |sink| {
+        let mut sent = 0;
+        while sink.send(sent).is_ok() {
+            sent += 1;
+        }
+        tx.send(sent).unwrap();
+    });
+    assert_eq!(block_on(stream.next()), Some(0));
+    drop(stream);
+
+    // the producer is held back by the bounded buffer, then told to stop
+    let sent = rx.recv().unwrap();
+    assert!(sent < 64, "producer ran ahead by {sent} items");
+}
+
+#[test]
+fn test_call_stream_propagates_panic() {
+    let mut stream = call_stream(ComModel::STA, |sink| {
+        sink.send(1).unwrap();
+        panic!("enumeration failed");
+    });
+    assert_eq!(block_on(stream.next()), Some(1));
+    let err = catch_unwind(AssertUnwindSafe(|| block_on(stream.next()))).unwrap_err();
+    assert_eq!(err.downcast_ref::<&str>(), Some(&"enumeration failed"));
+}
@@ -0,0 +1,97 @@
+use callcomapi_runtime::{CallError, ComModel, ComRuntime, current_apartment, schedule_after};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_schedule_after_runs_once_on_the_worker() {
+    let (tx, rx) = mpsc::channel();
+    let start = Instant::now();
+    let _task = schedule_after(ComModel::STA, Duration::from_millis(30), move || {
+        tx.send((start.elapsed(), current_apartment())).unwrap();
+    })
+    .unwrap();
+
+    let (elapsed, model) = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(elapsed >= Duration::from_millis(30));
+    assert_eq!(model, Some(ComModel::STA));
+    assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+}
+
+#[test]
+fn test_earlier_timer_wakes_a_waiting_worker() {
+    let rt = ComRuntime::new();
+    let (tx, rx) = mpsc::channel();
+    let late = tx.clone();
+    let _late = rt
+        .schedule_after(ComModel::MTA, Duration::from_secs(60), move || {
+            late.send("late").unwrap()
+        })
+        .unwrap();
+    // let the worker settle into waiting for the late timer
+    rt.call_sync(ComModel::MTA, || ());
+    let _early = rt
+        .schedule_after(ComModel::MTA, Duration::from_millis(10), move || {
+            tx.send("early").unwrap()
+        })
+        .unwrap();
+
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok("early"));
+}
+
+#[test]
+fn test_periodic_task_until_cancelled() {
+    let rt = ComRuntime::new();
+    let runs = Arc::new(AtomicUsize::new(0));
+    let (tx, rx) = mpsc::channel();
+    let task = {
+        let runs = runs.clone();
+        rt.schedule_every(ComModel::STA, Duration::from_millis(5), move || {
+            let n = runs.fetch_add(1, Ordering::SeqCst) + 1;
+            if n == 2 {
+                panic!("poll failed");
+            }
+            let _ = tx.send(n);
+        })
+        .unwrap()
+    };
+
+    // the panicking second run does not stop the third
+    while rx.recv_timeout(Duration::from_secs(5)).unwrap() < 3 {}
+    task.cancel();
+    assert!(task.is_cancelled());
+    // a run may have been in progress while cancelling
+    rt.call_sync(ComModel::STA, || ());
+    let after = runs.load(Ordering::SeqCst);
+    thread::sleep(Duration::from_millis(30));
+    assert_eq!(runs.load(Ordering::SeqCst), after);
+
+    rt.shutdown();
+    assert!(matches!(
+        rt.schedule_every(ComModel::STA, Duration::from_millis(5), || ()),
+        Err(CallError::ShutDown(ComModel::STA))
+    ));
+}
+
+#[test]
+fn test_slow_periodic_task_leaves_room_for_calls() {
+    let rt = Arc::new(ComRuntime::new());
+    let task = rt
+        .schedule_every(ComModel::MTA, Duration::from_millis(10), || {
+            thread::sleep(Duration::from_millis(30))
+        })
+        .unwrap();
+    // let the timer become overdue while its body runs
+    thread::sleep(Duration::from_millis(50));
+
+    let (tx, rx) = mpsc::channel();
+    let call = {
+        let rt = rt.clone();
+        thread::spawn(move || tx.send(rt.call_sync(ComModel::MTA, || 7)).unwrap())
+    };
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(7));
+    call.join().unwrap();
+    task.cancel();
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "tokio")]
+
+use callcomapi_runtime::{
+    ApartmentConfig, ComModel, TokioMode, block_on, call_async, call_sync, configure_apartment,
+};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn test_worker_runtime_drives_tokio_timers() {
+    let value = call_sync(ComModel::STA, || {
+        block_on(async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            5
+        })
+    });
+    assert_eq!(value, 5);
+
+    // sync bodies run inside the worker runtime's context as well
+    let flavor = call_sync(ComModel::STA, || {
+        tokio::runtime::Handle::current().runtime_flavor()
+    });
+    assert_eq!(flavor, tokio::runtime::RuntimeFlavor::CurrentThread);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_caller_handle_mode() {
+    configure_apartment(
+        ComModel::MTA,
+        ApartmentConfig::default().tokio_mode(TokioMode::CallerHandle),
+    );
+
+    let (tid, flavor) = call_async(ComModel::MTA, || {
+        block_on(async {
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            tokio::spawn(async move { tx.send(()).unwrap() });
+            rx.await.unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        });
+        (
+            thread::current().id(),
+            tokio::runtime::Handle::current().runtime_flavor(),
+        )
+    })
+    .await;
+    assert_ne!(tid, thread::current().id());
+    assert_eq!(flavor, tokio::runtime::RuntimeFlavor::MultiThread);
+}
@@ -0,0 +1,88 @@
+use callcomapi_runtime::{
+    ComModel, ComRuntime, HungTask, HungTaskPolicy, WatchdogConfig, call_sync, call_sync_labeled,
+    enable_watchdog, stats,
+};
+use futures::StreamExt;
+use futures::executor::block_on;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn test_watchdog_reports_and_replaces_hung_worker() {
+    let reports: Arc<Mutex<Vec<HungTask>>> = Arc::default();
+    let sink = reports.clone();
+    enable_watchdog(
+        WatchdogConfig::new(Duration::from_millis(50))
+            .poll_interval(Duration::from_millis(10))
+            .policy(HungTaskPolicy::Replace)
+            .on_hung(move |task| sink.lock().unwrap().push(task.clone())),
+    );
+
+    let hung_tid = call_sync(ComModel::MTA, || thread::current().id());
+    let stuck = thread::spawn(|| {
+        call_sync_labeled(ComModel::MTA, "stuck", || {
+            thread::sleep(Duration::from_millis(400));
+            thread::current().id()
+        })
+    });
+
+    // the watchdog flags the task and routes new work to a fresh worker
+    thread::sleep(Duration::from_millis(150));
+    let fresh_tid = call_sync(ComModel::MTA, || thread::current().id());
+    assert_ne!(hung_tid, fresh_tid);
+
+    {
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].label, "stuck");
+        assert_eq!(reports[0].model, ComModel::MTA);
+        assert!(reports[0].elapsed >= Duration::from_millis(50));
+    }
+
+    let mta = stats().apartment(ComModel::MTA).cloned().unwrap();
+    assert_eq!(mta.hung_tasks, 1);
+    assert_eq!(mta.replacements, 1);
+    assert!(mta.healthy);
+
+    // the hung task still completes on its original worker
+    assert_eq!(stuck.join().unwrap(), hung_tid);
+}
+
+#[test]
+fn test_watchdog_leaves_sessions_and_streams_alone() {
+    let rt = ComRuntime::new();
+    let reports: Arc<Mutex<Vec<HungTask>>> = Arc::default();
+    let sink = reports.clone();
+    rt.enable_watchdog(
+        WatchdogConfig::new(Duration::from_millis(30))
+            .poll_interval(Duration::from_millis(5))
+            .policy(HungTaskPolicy::Replace)
+            .on_hung(move |task| sink.lock().unwrap().push(task.clone())),
+    );
+
+    let mut session = rt.open_session(ComModel::STA, |requests, responses| {
+        for () in requests {
+            responses.send(thread::current().id()).unwrap();
+        }
+    });
+    let worker = session.request(()).unwrap();
+    let mut items = rt.call_stream(ComModel::MTA, |sink| {
+        sink.send(1).unwrap();
+        thread::sleep(Duration::from_millis(150));
+        sink.send(2).unwrap();
+    });
+    thread::sleep(Duration::from_millis(150));
+
+    // the session still has its worker
+    assert_eq!(session.request(()), Some(worker));
+    session.close();
+    let items: Vec<i32> = block_on((&mut items).collect());
+    assert_eq!(items, [1, 2]);
+
+    assert!(reports.lock().unwrap().is_empty());
+    for model in [ComModel::STA, ComModel::MTA] {
+        let stats = rt.stats().apartment(model).cloned().unwrap();
+        assert_eq!((stats.hung_tasks, stats.replacements), (0, 0));
+    }
+}